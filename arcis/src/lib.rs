@@ -14,13 +14,53 @@ use arcis_imports::*;
 mod circuits {
     use arcis_imports::*;
 
+    /// Identifier of a token mint inside encrypted state
+    /// 
+    /// The first 16 bytes of the mint address read as a little-endian
+    /// `u128`, which fits in a single field element and is still far too
+    /// wide for a colliding mint address to be ground out.
+    pub type MintId = u128;
+
+    /// `MintId` of native SOL (the wrapped SOL mint `So11111111111111111111111111111111111111112`)
+    pub const NATIVE_MINT: MintId = 0x35c01846637f68fb8481abfe57889b06;
+
+    /// Identifier of a wallet inside encrypted state
+    /// 
+    /// Derived from the wallet address the same way as `MintId`.
+    pub type AccountId = u128;
+
+    /// Number of `u128` words in a `Memo`
+    pub const MEMO_WORDS: usize = 4;
+
+    /// Fixed-size 64-byte memo
+    /// 
+    /// The bytes are packed little-endian into `u128` words (16 bytes each)
+    /// and zero-padded, so each word fits in a single field element.
+    pub struct Memo {
+        pub words: [u128; MEMO_WORDS],
+    }
+
     /// Represents a confidential transfer amount
     /// The amount is encrypted before being sent to the MPC network
     pub struct TransferAmount {
-        /// The transfer amount in lamports (1 SOL = 1_000_000_000 lamports)
+        /// The transfer amount in base units of `mint` (1 SOL = 1_000_000_000 lamports)
         pub amount: u64,
         /// Minimum balance required (for validation)
         pub min_balance: u64,
+        /// Network fee in lamports paid on top of the amount; only counted
+        /// against `min_balance` when `mint` is `NATIVE_MINT`
+        pub fee: u64,
+        /// Rent-exempt minimum in lamports that must remain after amount and
+        /// fee; only counted when `mint` is `NATIVE_MINT`
+        pub rent_exempt_floor: u64,
+        /// Mint of the transferred token
+        pub mint: MintId,
+        /// Decimals of `mint`, so the recipient can display the amount
+        pub decimals: u8,
+        /// Private memo passed through to the recipient
+        pub memo: Memo,
+        /// Must equal the sender's current sequence number (replay protection)
+        pub sequence: u64,
     }
 
     /// Validates and processes a confidential transfer
@@ -36,6 +76,9 @@ mod circuits {
     /// # Returns
     /// * `true` if the transfer amount is valid (> 0 and sender has sufficient balance)
     /// * `false` if validation fails
+    /// 
+    /// This plain check ignores `fee` and `rent_exempt_floor` and is kept for
+    /// compatibility; use `validate_transfer_status` for fee-aware validation.
     #[instruction]
     pub fn validate_transfer(transfer: Enc<Shared, TransferAmount>) -> bool {
         let data = transfer.to_arcis();
@@ -47,18 +90,420 @@ mod circuits {
         is_valid.reveal()
     }
 
-    /// Computes the encrypted transfer and returns a commitment
+    // Transfer status codes revealed by `validate_transfer_status` and the
+    // other instructions that report a rejection reason.
+    //
+    // This numeric mapping is a stable ABI mirrored by `TRANSFER_STATUS` in
+    // `src/lib/arcium.ts`: a value never changes meaning once published,
+    // and new rejection reasons are only ever appended.
+
+    /// The transfer passed every check
+    pub const TRANSFER_OK: u8 = 0;
+    /// The amount is zero
+    pub const TRANSFER_ZERO_AMOUNT: u8 = 1;
+    /// The sender's balance does not cover the transfer
+    pub const TRANSFER_INSUFFICIENT_FUNDS: u8 = 2;
+    /// The amount is above the per-transaction limit
+    pub const TRANSFER_EXCEEDS_TX_LIMIT: u8 = 3;
+    /// The amount plus the network fee does not fit in a `u64`
+    pub const TRANSFER_FEE_OVERFLOW: u8 = 4;
+    /// The transfer's mint does not match the account being debited
+    pub const TRANSFER_MINT_MISMATCH: u8 = 5;
+    /// The amount would take the account past its daily spending limit
+    pub const TRANSFER_DAILY_LIMIT_EXCEEDED: u8 = 6;
+    /// The sequence number doesn't match, i.e. the input was replayed or is stale
+    pub const TRANSFER_REPLAYED: u8 = 7;
+    /// Crediting the recipient would overflow its balance
+    pub const TRANSFER_RECIPIENT_OVERFLOW: u8 = 8;
+    /// The allowance or the owner's balance doesn't cover the amount
+    /// (deliberately not distinguished, see `spend_allowance`)
+    pub const TRANSFER_ALLOWANCE_REJECTED: u8 = 9;
+    /// The sender and recipient are the same account
+    pub const TRANSFER_SELF_TRANSFER: u8 = 10;
+    /// A state passed alongside the transfer (e.g. the spending policy)
+    /// belongs to a different account
+    pub const TRANSFER_ACCOUNT_MISMATCH: u8 = 11;
+    /// The terms of a vesting grant or payment stream are invalid
+    pub const TRANSFER_INVALID_TERMS: u8 = 12;
+
+    /// Computes the status code for a transfer
+    /// 
+    /// Every check is evaluated on every call and only the selected code
+    /// depends on the secret inputs. When several checks fail, the first
+    /// one in the order below is reported.
+    /// 
+    /// For native SOL the sender must keep `rent_exempt_floor` after paying
+    /// both amount and fee, i.e. `min_balance - amount - fee >=
+    /// rent_exempt_floor`. Both are in lamports, so for any other mint they
+    /// are paid from a different balance and only `amount` is checked
+    /// against `min_balance`. The sums are widened to `u128` so they cannot
+    /// wrap.
+    fn transfer_status(data: &TransferAmount, mint: MintId, max_amount: u64) -> u8 {
+        let native = mint == NATIVE_MINT;
+        let fee = if native { data.fee } else { 0 };
+        let floor = if native { data.rent_exempt_floor } else { 0 };
+
+        let debit = (data.amount as u128) + (fee as u128);
+        let required = debit + (floor as u128);
+
+        let wrong_mint = data.mint != mint;
+        let is_zero = data.amount == 0;
+        let over_limit = data.amount > max_amount;
+        let fee_overflow = debit > (u64::MAX as u128);
+        let insufficient = (data.min_balance as u128) < required;
+
+        if wrong_mint {
+            TRANSFER_MINT_MISMATCH
+        } else if is_zero {
+            TRANSFER_ZERO_AMOUNT
+        } else if over_limit {
+            TRANSFER_EXCEEDS_TX_LIMIT
+        } else if fee_overflow {
+            TRANSFER_FEE_OVERFLOW
+        } else if insufficient {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        }
+    }
+
+    /// Validates a confidential transfer and reports why it was rejected
+    /// 
+    /// Unlike `validate_transfer`, this accounts for the network fee and the
+    /// rent-exempt floor of native SOL transfers and enforces a public
+    /// per-transaction limit. The
+    /// revealed value is one of the `TRANSFER_*` status codes instead of a
+    /// bare bool. Nothing beyond the code is revealed.
+    /// 
+    /// # Arguments
+    /// * `transfer` - Encrypted transfer details
+    /// * `mint` - Public mint of the account being debited
+    /// * `max_amount` - Public per-transaction limit (`u64::MAX` for none)
+    /// 
+    /// # Returns
+    /// * `TRANSFER_OK` if the transfer is valid, otherwise the rejection reason
+    #[instruction]
+    pub fn validate_transfer_status(
+        transfer: Enc<Shared, TransferAmount>,
+        mint: MintId,
+        max_amount: u64,
+    ) -> u8 {
+        let data = transfer.to_arcis();
+
+        transfer_status(&data, mint, max_amount).reveal()
+    }
+
+    /// Maximum number of transfers in one `validate_transfer_batch` call
+    pub const MAX_BATCH_SIZE: usize = 16;
+
+    /// Fixed-capacity batch of confidential transfers
+    /// 
+    /// Only the first `count` entries are validated. The remaining slots are
+    /// padding so that every batch has the same shape, and are always
+    /// reported as invalid. The revealed bitmask therefore bounds `count`
+    /// from below, and reveals it exactly when every entry is valid.
+    pub struct TransferBatch {
+        pub transfers: [TransferAmount; MAX_BATCH_SIZE],
+        pub count: u8,
+    }
+
+    /// Validates a whole batch of transfers in a single computation
+    /// 
+    /// Each entry goes through the same checks as `validate_transfer_status`.
+    /// Payroll and airdrop flows use this to avoid one MPC round trip per
+    /// transfer.
+    /// 
+    /// # Arguments
+    /// * `batch` - Encrypted batch of transfers and the number of used slots
+    /// * `mint` - Public mint of the account being debited
+    /// * `max_amount` - Public per-transaction limit applied to every entry
+    /// 
+    /// # Returns
+    /// * Bitmask where bit `i` is set if entry `i` is valid
+    /// * Sum of the valid amounts, encrypted to the sender
+    #[instruction]
+    pub fn validate_transfer_batch(
+        batch: Enc<Shared, TransferBatch>,
+        mint: MintId,
+        max_amount: u64,
+    ) -> (u16, Enc<Shared, u128>) {
+        let data = batch.to_arcis();
+
+        let mut valid = [false; MAX_BATCH_SIZE];
+        let mut total: u128 = 0;
+        for i in 0..MAX_BATCH_SIZE {
+            let entry = &data.transfers[i];
+            valid[i] = ((i as u8) < data.count)
+                && (transfer_status(entry, mint, max_amount) == TRANSFER_OK);
+            if valid[i] {
+                total += entry.amount as u128;
+            }
+        }
+
+        // Shift bits in from the top so entry 0 ends up in the lowest bit
+        let mut valid_mask: u16 = 0;
+        for i in (0..MAX_BATCH_SIZE).rev() {
+            valid_mask = valid_mask * 2 + (valid[i] as u16);
+        }
+
+        (valid_mask.reveal(), batch.owner.from_arcis(total))
+    }
+
+    /// Length of the rolling spending window in slots (~24h at 400ms per slot)
+    pub const SLOTS_PER_DAY: u64 = 216_000;
+
+    /// Spending limits chosen by the account owner
+    pub struct SpendingLimits {
+        /// Maximum amount of a single transfer
+        pub per_tx_max: u64,
+        /// Maximum total amount per window of `SLOTS_PER_DAY` slots
+        pub daily_max: u64,
+    }
+
+    /// Per-account spending policy held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, SpendingPolicy>`. Neither the limits nor
+    /// the running total are ever revealed.
+    /// 
+    /// Every instruction that debits a balance takes the owner's policy for
+    /// that mint and enforces it, so the limits can't be sidestepped by
+    /// picking a different instruction. Accounts without limits use a policy
+    /// with both maximums set to `u64::MAX`.
+    pub struct SpendingPolicy {
+        /// Account whose debits the policy limits
+        pub owner: AccountId,
+        /// Mint the limits are denominated in
+        pub mint: MintId,
+        /// Maximum amount of a single transfer
+        pub per_tx_max: u64,
+        /// Maximum total amount per window of `SLOTS_PER_DAY` slots
+        pub daily_max: u64,
+        /// Amount spent since `window_start_slot`
+        pub spent_in_window: u64,
+        /// Slot at which the current window started
+        pub window_start_slot: u64,
+    }
+
+    /// Creates a spending policy with an empty window starting at `current_slot`
+    #[instruction]
+    pub fn open_spending_policy(
+        mxe: Mxe,
+        owner: AccountId,
+        mint: MintId,
+        limits: Enc<Shared, SpendingLimits>,
+        current_slot: u64,
+    ) -> Enc<Mxe, SpendingPolicy> {
+        let data = limits.to_arcis();
+
+        mxe.from_arcis(SpendingPolicy {
+            owner,
+            mint,
+            per_tx_max: data.per_tx_max,
+            daily_max: data.daily_max,
+            spent_in_window: 0,
+            window_start_slot: current_slot,
+        })
+    }
+
+    /// Replaces the limits of an existing policy; only the owner can
+    /// 
+    /// The running total and window are kept, so changing limits can't be
+    /// used to reset the amount already spent today.
+    /// 
+    /// # Returns
+    /// * The updated policy, re-encrypted to the MXE
+    /// * `true` if `owner` matches the policy's owner
+    #[instruction]
+    pub fn update_spending_limits(
+        policy: Enc<Mxe, SpendingPolicy>,
+        limits: Enc<Shared, SpendingLimits>,
+        owner: AccountId,
+    ) -> (Enc<Mxe, SpendingPolicy>, bool) {
+        let mut state = policy.to_arcis();
+        let data = limits.to_arcis();
+
+        let updated = state.owner == owner;
+        if updated {
+            state.per_tx_max = data.per_tx_max;
+            state.daily_max = data.daily_max;
+        }
+
+        (policy.owner.from_arcis(state), updated.reveal())
+    }
+
+    /// Checks a debit of `amount` from `owner`'s balance of `mint` against
+    /// `policy`
+    /// 
+    /// The window rolls over once `SLOTS_PER_DAY` slots have passed since it
+    /// started. An earlier rejection in `status` takes precedence, as in
+    /// `consume_sequence`. Nothing is added to the running total here; the
+    /// caller does that with `record_spend` once the debit is final.
+    fn limit_status(
+        policy: &mut SpendingPolicy,
+        owner: AccountId,
+        mint: MintId,
+        amount: u64,
+        current_slot: u64,
+        status: u8,
+    ) -> u8 {
+        if current_slot >= policy.window_start_slot + SLOTS_PER_DAY {
+            policy.spent_in_window = 0;
+            policy.window_start_slot = current_slot;
+        }
+
+        let spent_after = (policy.spent_in_window as u128) + (amount as u128);
+
+        if status != TRANSFER_OK {
+            status
+        } else if (policy.owner != owner) || (policy.mint != mint) {
+            TRANSFER_ACCOUNT_MISMATCH
+        } else if amount > policy.per_tx_max {
+            TRANSFER_EXCEEDS_TX_LIMIT
+        } else if spent_after > (policy.daily_max as u128) {
+            TRANSFER_DAILY_LIMIT_EXCEEDED
+        } else {
+            TRANSFER_OK
+        }
+    }
+
+    /// Adds a debit to the policy's running total if it went through
+    fn record_spend(policy: &mut SpendingPolicy, amount: u64, status: u8) {
+        if status == TRANSFER_OK {
+            policy.spent_in_window += amount;
+        }
+    }
+
+    /// Length in bytes of a commitment blinding factor
+    pub const COMMITMENT_BLINDING_BYTES: usize = 32;
+
+    /// SHA3-256 commitment to an amount and a blinding factor
+    /// 
+    /// The preimage is the amount as 8 little-endian bytes followed by the
+    /// blinding. It has a fixed length, so every (amount, blinding) pair has
+    /// a distinct encoding. Hiding rests on the 256-bit blinding, and
+    /// binding on the collision resistance of SHA3-256.
+    fn commitment_hash(amount: u64, blinding: [u8; COMMITMENT_BLINDING_BYTES]) -> [u8; 32] {
+        let amount_bytes = amount.to_le_bytes();
+
+        let mut preimage = [0u8; 8 + COMMITMENT_BLINDING_BYTES];
+        for i in 0..8 {
+            preimage[i] = amount_bytes[i];
+        }
+        for i in 0..COMMITMENT_BLINDING_BYTES {
+            preimage[8 + i] = blinding[i];
+        }
+
+        SHA3_256::new().digest(&preimage)
+    }
+
+    /// Opening of a transfer commitment
+    pub struct CommitmentOpening {
+        pub amount: u64,
+        pub blinding: [u8; COMMITMENT_BLINDING_BYTES],
+    }
+
+    /// Computes a hiding, binding commitment to the transfer amount
+    /// 
+    /// The blinding factor is drawn from MPC randomness, so no node learns
+    /// it, and the commitment reveals nothing about the amount on its own.
+    /// The blinding is returned encrypted to the sender, who can later open
+    /// the commitment with `open_transfer_commitment`.
+    /// 
+    /// # Arguments
+    /// * `transfer` - Encrypted transfer details
     /// 
-    /// This allows the transfer to be verified without revealing the amount.
-    /// The commitment can be used to prove the transfer occurred.
+    /// # Returns
+    /// * The public 256-bit commitment
+    /// * The blinding factor, encrypted to the sender
     #[instruction]
-    pub fn compute_transfer_commitment(transfer: Enc<Shared, TransferAmount>) -> u64 {
+    pub fn compute_transfer_commitment(
+        transfer: Enc<Shared, TransferAmount>,
+    ) -> ([u8; 32], Enc<Shared, [u8; COMMITMENT_BLINDING_BYTES]>) {
         let data = transfer.to_arcis();
         
-        // Return a hash-like commitment of the amount
-        // This proves the computation happened without revealing the value
-        let commitment = data.amount ^ 0xDEADBEEF_CAFEBABE;
-        commitment.reveal()
+        let blinding = ArcisRNG::gen_uniform::<[u8; COMMITMENT_BLINDING_BYTES]>();
+        let commitment = commitment_hash(data.amount, blinding);
+
+        (commitment.reveal(), transfer.owner.from_arcis(blinding))
+    }
+
+    /// Checks that an opening matches a previously revealed commitment
+    /// 
+    /// Only the boolean result is revealed, so a failed opening does not
+    /// leak the amount or blinding being tried.
+    #[instruction]
+    pub fn open_transfer_commitment(
+        opening: Enc<Shared, CommitmentOpening>,
+        commitment: [u8; 32],
+    ) -> bool {
+        let data = opening.to_arcis();
+        
+        let matches = commitment_hash(data.amount, data.blinding) == commitment;
+        matches.reveal()
+    }
+
+    /// Transfer amount as seen by the recipient
+    pub struct RecipientAmount {
+        /// The transfer amount in base units of `mint`
+        pub amount: u64,
+        /// Mint of the transferred token
+        pub mint: MintId,
+        /// Decimals of `mint`
+        pub decimals: u8,
+        /// The sender's memo
+        pub memo: Memo,
+    }
+
+    /// The parts of a transfer the recipient is allowed to see
+    fn recipient_view(data: &TransferAmount) -> RecipientAmount {
+        RecipientAmount {
+            amount: data.amount,
+            mint: data.mint,
+            decimals: data.decimals,
+            memo: Memo { words: data.memo.words },
+        }
+    }
+
+    /// Re-encrypts the transfer amount and memo to the recipient's x25519 key
+    /// 
+    /// The sender's `min_balance` and fee details are dropped, so the
+    /// recipient learns the amount, token and memo and nothing else. The
+    /// output ciphertext is stored alongside the recipient's transaction
+    /// history entry.
+    /// 
+    /// `recipient` is chosen by the caller, so the on-chain program must
+    /// only queue this for the owner of `sender_balance`. The transfer's
+    /// sequence number is consumed as well, so a captured ciphertext can't
+    /// be re-encrypted a second time; a replayed input yields an all-zero
+    /// output. A ciphertext used here can't also be submitted as a
+    /// transfer, which returns its own recipient copy.
+    /// 
+    /// # Arguments
+    /// * `sender_balance` - Sender's encrypted balance state
+    /// * `transfer` - Encrypted transfer details from the sender
+    /// * `recipient` - Recipient's x25519 public key and nonce
+    /// 
+    /// # Returns
+    /// * The sender's balance with its sequence number consumed
+    /// * The recipient's view of the transfer, encrypted to `recipient`
+    /// * `TRANSFER_OK`, or `TRANSFER_REPLAYED` if the sequence number didn't match
+    #[instruction]
+    pub fn reencrypt_to_recipient(
+        sender_balance: Enc<Mxe, Balance>,
+        transfer: Enc<Shared, TransferAmount>,
+        recipient: Shared,
+    ) -> (Enc<Mxe, Balance>, Enc<Shared, RecipientAmount>, u8) {
+        let mut sender = sender_balance.to_arcis();
+        let data = transfer.to_arcis();
+
+        let status = consume_sequence(&mut sender.sequence, data.sequence, TRANSFER_OK);
+        let view = recipient_view(&disclosed_transfer(&data, status == TRANSFER_OK));
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient.from_arcis(view),
+            status.reveal(),
+        )
     }
 
     /// Encrypted balance check
@@ -79,29 +524,2469 @@ mod circuits {
         sufficient.reveal()
     }
 
-    /// Private comparison for gaming/auctions
+    /// Maximum number of recipients in one `split_payment` call
+    pub const MAX_SPLIT_RECIPIENTS: usize = 4;
+
+    /// Encrypted per-recipient amounts for a split payment
     /// 
-    /// Compares two hidden values and returns which is larger
-    /// without revealing the actual values.
-    pub struct HiddenComparison {
-        pub value_a: u64,
-        pub value_b: u64,
+    /// The memo is shared by every recipient.
+    pub struct SplitPayment {
+        pub amounts: [u64; MAX_SPLIT_RECIPIENTS],
+        pub mint: MintId,
+        pub decimals: u8,
+        pub memo: Memo,
+        /// Must equal the sender's current sequence number
+        pub sequence: u64,
+    }
+
+    /// Credits `amount` to a split payment recipient's pending balance if
+    /// `apply` is set
+    fn credit_split(recipient: &mut Balance, amount: u64, apply: bool) {
+        if apply {
+            recipient.pending += amount;
+        }
     }
 
+    /// Splits one payment from the sender's balance across several
+    /// recipients atomically
+    /// 
+    /// The total is debited from the sender's available balance and each
+    /// amount is credited to its recipient's pending balance, with the same
+    /// checks as `confidential_transfer`: mints must match, the total must
+    /// be non-zero and covered by the sender, it must pass the sender's
+    /// spending policy, and `payment.sequence` must match the sender's
+    /// sequence number. Either every recipient is paid or nothing moves.
+    /// 
+    /// Only the first `count` recipient slots are used; amounts past it are
+    /// ignored. The used recipients must be distinct accounts other than the
+    /// sender, and the on-chain program must only write back the first
+    /// `count` recipient balances, so unused slots can repeat any of them.
+    /// 
+    /// # Arguments
+    /// * `sender_balance` - Sender's encrypted balance state
+    /// * `recipient_balance_0` .. `recipient_balance_3` - Recipients'
+    ///   encrypted balance states, in the same order as `amounts`
+    /// * `policy` - Sender's spending policy for the balance's mint
+    /// * `payment` - Encrypted per-recipient amounts from the sender
+    /// * `recipient_0` .. `recipient_3` - Recipient x25519 keys, in the same
+    ///   order as `amounts`
+    /// * `count` - Number of recipient slots in use
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * Updated sender balance, recipient balances and the sender's
+    ///   policy, re-encrypted to the MXE
+    /// * Each recipient's amount, re-encrypted to that recipient (zero
+    ///   unless the payment was applied)
+    /// * `TRANSFER_OK` if the payment was applied, otherwise the rejection reason
     #[instruction]
-    pub fn compare_hidden_values(comparison: Enc<Shared, HiddenComparison>) -> u8 {
-        let data = comparison.to_arcis();
-        
-        // Return: 0 = equal, 1 = a > b, 2 = b > a
-        let result = if data.value_a == data.value_b {
-            0u8
-        } else if data.value_a > data.value_b {
-            1u8
+    pub fn split_payment(
+        sender_balance: Enc<Mxe, Balance>,
+        recipient_balance_0: Enc<Mxe, Balance>,
+        recipient_balance_1: Enc<Mxe, Balance>,
+        recipient_balance_2: Enc<Mxe, Balance>,
+        recipient_balance_3: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        payment: Enc<Shared, SplitPayment>,
+        recipient_0: Shared,
+        recipient_1: Shared,
+        recipient_2: Shared,
+        recipient_3: Shared,
+        count: u8,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, SpendingPolicy>,
+        Enc<Shared, RecipientAmount>,
+        Enc<Shared, RecipientAmount>,
+        Enc<Shared, RecipientAmount>,
+        Enc<Shared, RecipientAmount>,
+        u8,
+    ) {
+        let mut sender = sender_balance.to_arcis();
+        let mut credited_0 = recipient_balance_0.to_arcis();
+        let mut credited_1 = recipient_balance_1.to_arcis();
+        let mut credited_2 = recipient_balance_2.to_arcis();
+        let mut credited_3 = recipient_balance_3.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = payment.to_arcis();
+
+        let owners = [credited_0.owner, credited_1.owner, credited_2.owner, credited_3.owner];
+        let mints = [credited_0.mint, credited_1.mint, credited_2.mint, credited_3.mint];
+        let pending = [
+            credited_0.pending,
+            credited_1.pending,
+            credited_2.pending,
+            credited_3.pending,
+        ];
+
+        // Sum in u128 so a large set of amounts cannot wrap past the balance
+        let mut amounts = [0u64; MAX_SPLIT_RECIPIENTS];
+        let mut total: u128 = 0;
+        let mut mint_mismatch = data.mint != sender.mint;
+        let mut repeated = false;
+        let mut overflow = false;
+        for i in 0..MAX_SPLIT_RECIPIENTS {
+            let used = i < count as usize;
+            amounts[i] = if used { data.amounts[i] } else { 0 };
+            total += amounts[i] as u128;
+
+            if used && (mints[i] != data.mint) {
+                mint_mismatch = true;
+            }
+            if used && (owners[i] == sender.owner) {
+                repeated = true;
+            }
+            for j in 0..MAX_SPLIT_RECIPIENTS {
+                if used && (j < i) && (owners[j] == owners[i]) {
+                    repeated = true;
+                }
+            }
+            if pending[i] > u64::MAX - amounts[i] {
+                overflow = true;
+            }
+        }
+
+        let status = if mint_mismatch {
+            TRANSFER_MINT_MISMATCH
+        } else if repeated {
+            TRANSFER_SELF_TRANSFER
+        } else if total == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if (sender.available as u128) < total {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else if overflow {
+            TRANSFER_RECIPIENT_OVERFLOW
         } else {
-            2u8
+            TRANSFER_OK
         };
-        
-        result.reveal()
+        // Only used as a u64 once the total is known to fit the balance
+        let debit = total as u64;
+        let status = limit_status(
+            &mut limits,
+            sender.owner,
+            sender.mint,
+            debit,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+
+        let applied = status == TRANSFER_OK;
+        let mut paid = [0u64; MAX_SPLIT_RECIPIENTS];
+        if applied {
+            sender.available -= debit;
+            paid = amounts;
+        }
+        credit_split(&mut credited_0, paid[0], applied);
+        credit_split(&mut credited_1, paid[1], applied);
+        credit_split(&mut credited_2, paid[2], applied);
+        credit_split(&mut credited_3, paid[3], applied);
+        record_spend(&mut limits, debit, status);
+
+        let payout = |i: usize| RecipientAmount {
+            amount: paid[i],
+            mint: data.mint,
+            decimals: data.decimals,
+            memo: Memo { words: data.memo.words },
+        };
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient_balance_0.owner.from_arcis(credited_0),
+            recipient_balance_1.owner.from_arcis(credited_1),
+            recipient_balance_2.owner.from_arcis(credited_2),
+            recipient_balance_3.owner.from_arcis(credited_3),
+            policy.owner.from_arcis(limits),
+            recipient_0.from_arcis(payout(0)),
+            recipient_1.from_arcis(payout(1)),
+            recipient_2.from_arcis(payout(2)),
+            recipient_3.from_arcis(payout(3)),
+            status.reveal(),
+        )
+    }
+
+    /// Confidential balance held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, Balance>`, so no single party
+    /// (including the account owner) can read it outside the MPC cluster.
+    /// Each balance holds a single mint.
+    /// 
+    /// Incoming credits land in `pending` and only outgoing debits touch
+    /// `available`, so transfers to an account never race with the owner's
+    /// own transfers out of it. The owner merges the two with
+    /// `apply_pending_balance`.
+    pub struct Balance {
+        /// Spendable balance in base units of `mint`
+        pub available: u64,
+        /// Received but not yet applied, in base units of `mint`
+        pub pending: u64,
+        /// Mint this balance is denominated in
+        pub mint: MintId,
+        /// Account allowed to apply pending credits
+        pub owner: AccountId,
+        /// Sequence number the next outgoing transfer must carry
+        pub sequence: u64,
+    }
+
+    /// Creates an empty MXE-held balance of `mint` for a new account
+    #[instruction]
+    pub fn open_balance(mxe: Mxe, owner: AccountId, mint: MintId) -> Enc<Mxe, Balance> {
+        mxe.from_arcis(Balance {
+            available: 0,
+            pending: 0,
+            mint,
+            owner,
+            sequence: 0,
+        })
+    }
+
+    /// Moves received credits from `pending` into `available`
+    /// 
+    /// Only the balance's owner can apply them. The balance is left
+    /// unchanged if the merged amount would overflow.
+    /// 
+    /// # Returns
+    /// * The updated balance, re-encrypted to the MXE
+    /// * `true` if the pending credits were applied
+    #[instruction]
+    pub fn apply_pending_balance(
+        balance: Enc<Mxe, Balance>,
+        owner: AccountId,
+    ) -> (Enc<Mxe, Balance>, bool) {
+        let mut data = balance.to_arcis();
+
+        let applied = (data.owner == owner) && (data.available <= u64::MAX - data.pending);
+        if applied {
+            data.available += data.pending;
+            data.pending = 0;
+        }
+
+        (balance.owner.from_arcis(data), applied.reveal())
+    }
+
+    /// Re-encrypts the balance's current sequence number to its owner
+    /// 
+    /// Lets a client that lost track of its sequence number resynchronize.
+    /// The on-chain program must only queue this for the account owner.
+    #[instruction]
+    pub fn read_sequence(balance: Enc<Mxe, Balance>, owner: Shared) -> Enc<Shared, u64> {
+        let data = balance.to_arcis();
+
+        owner.from_arcis(data.sequence)
+    }
+
+    /// Checks an input's sequence number against the expected counter
+    /// 
+    /// A matching sequence number is consumed even when `status` rejects the
+    /// transfer for another reason, so the same ciphertext can't succeed
+    /// later (for instance once the sender has been topped up). A mismatch
+    /// overrides `status` with `TRANSFER_REPLAYED`.
+    fn consume_sequence(expected: &mut u64, sequence: u64, status: u8) -> u8 {
+        let replayed = sequence != *expected;
+        if !replayed {
+            *expected += 1;
+        }
+
+        if replayed {
+            TRANSFER_REPLAYED
+        } else {
+            status
+        }
+    }
+
+    /// Status of moving `data.amount` from `sender` to `recipient`, not
+    /// counting replay protection
+    /// 
+    /// Passing the same account as both sides would produce two diverging
+    /// copies of one balance, so a transfer to oneself is rejected.
+    fn ledger_status(sender: &Balance, recipient: &Balance, data: &TransferAmount) -> u8 {
+        if (data.mint != sender.mint) || (data.mint != recipient.mint) {
+            TRANSFER_MINT_MISMATCH
+        } else if sender.owner == recipient.owner {
+            TRANSFER_SELF_TRANSFER
+        } else if data.amount == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if sender.available < data.amount {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else if recipient.pending > u64::MAX - data.amount {
+            TRANSFER_RECIPIENT_OVERFLOW
+        } else {
+            TRANSFER_OK
+        }
+    }
+
+    /// Moves `data.amount` from the sender's available balance to the
+    /// recipient's pending balance if the transfer is valid
+    /// 
+    /// The transfer must also pass the sender's spending policy, and an
+    /// earlier rejection in `status` takes precedence. Both balances are
+    /// left untouched unless the final status is `TRANSFER_OK`. The sender's
+    /// sequence number is consumed whenever it matches.
+    fn apply_transfer(
+        sender: &mut Balance,
+        recipient: &mut Balance,
+        policy: &mut SpendingPolicy,
+        data: &TransferAmount,
+        current_slot: u64,
+        status: u8,
+    ) -> u8 {
+        let status = if status == TRANSFER_OK {
+            ledger_status(sender, recipient, data)
+        } else {
+            status
+        };
+        let status = limit_status(
+            policy,
+            sender.owner,
+            sender.mint,
+            data.amount,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+
+        if status == TRANSFER_OK {
+            sender.available -= data.amount;
+            recipient.pending += data.amount;
+        }
+        record_spend(policy, data.amount, status);
+
+        status
+    }
+
+    /// Executes a confidential transfer between two MXE-held balances
+    /// 
+    /// The amount is decrypted only inside the MPC environment, checked
+    /// against the sender's available balance, and credited to the
+    /// recipient's pending balance. `transfer.sequence` must match the
+    /// sender's sequence number, so a captured ciphertext can't be
+    /// submitted twice.
+    /// 
+    /// The recipient's view of the transfer, including the memo, is
+    /// re-encrypted to `recipient` as part of the same computation; it is
+    /// all zero unless the transfer was applied. `recipient` is chosen by
+    /// the caller, so the on-chain program must only queue this for the
+    /// owner of `sender_balance`.
+    /// 
+    /// # Arguments
+    /// * `sender_balance` - Sender's encrypted balance state
+    /// * `recipient_balance` - Recipient's encrypted balance state
+    /// * `policy` - Sender's spending policy for the balance's mint
+    /// * `transfer` - Encrypted transfer details from the sender
+    /// * `recipient` - Recipient's x25519 public key and nonce
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * Updated sender and recipient balances and the sender's policy,
+    ///   re-encrypted to the MXE
+    /// * The recipient's view of the transfer, encrypted to `recipient`
+    /// * `TRANSFER_OK` if the transfer was applied, otherwise the rejection reason
+    #[instruction]
+    pub fn confidential_transfer(
+        sender_balance: Enc<Mxe, Balance>,
+        recipient_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        transfer: Enc<Shared, TransferAmount>,
+        recipient: Shared,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, SpendingPolicy>,
+        Enc<Shared, RecipientAmount>,
+        u8,
+    ) {
+        let mut sender = sender_balance.to_arcis();
+        let mut credited = recipient_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = transfer.to_arcis();
+
+        let status = apply_transfer(
+            &mut sender,
+            &mut credited,
+            &mut limits,
+            &data,
+            current_slot,
+            TRANSFER_OK,
+        );
+        let view = recipient_view(&disclosed_transfer(&data, status == TRANSFER_OK));
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient_balance.owner.from_arcis(credited),
+            policy.owner.from_arcis(limits),
+            recipient.from_arcis(view),
+            status.reveal(),
+        )
+    }
+
+    /// Auditor registration held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, AuditorConfig>` next to the account's
+    /// balance. Audited transfer instructions read the key from here, so
+    /// the caller can't pick a different auditor per transfer, and check
+    /// `account` against the sender, so one account's config can't stand
+    /// in for another's.
+    /// 
+    /// Only the current auditor's `authority` can rotate the key, so the
+    /// audited account can't switch disclosure off by swapping in a key it
+    /// controls. The on-chain program must only queue `register_auditor`
+    /// for the auditor, and route every transfer from an account with a
+    /// registered auditor through the audited instructions.
+    pub struct AuditorConfig {
+        /// Account whose transfers are disclosed
+        pub account: AccountId,
+        /// x-coordinate of the auditor's x25519 public key (MXE state can
+        /// only hold numeric data, so the key is rebuilt when used)
+        pub auditor: BaseField,
+        /// Wallet allowed to rotate the auditor key
+        pub authority: AccountId,
+        /// Bumped on every rotation, so old and new keys can be told apart
+        pub epoch: u32,
+    }
+
+    /// Registers an auditor for an account
+    #[instruction]
+    pub fn register_auditor(
+        mxe: Mxe,
+        account: AccountId,
+        auditor: ArcisX25519Pubkey,
+        authority: AccountId,
+    ) -> Enc<Mxe, AuditorConfig> {
+        mxe.from_arcis(AuditorConfig {
+            account,
+            auditor: auditor.to_x(),
+            authority,
+            epoch: 0,
+        })
+    }
+
+    /// Replaces the registered auditor key and authority; only the current
+    /// authority can
+    /// 
+    /// # Arguments
+    /// * `config` - The account's encrypted auditor config
+    /// * `auditor` - The new auditor's x25519 public key
+    /// * `new_authority` - Wallet allowed to rotate the key from now on
+    /// * `authority` - Public identity of the caller
+    /// 
+    /// # Returns
+    /// * The updated config, re-encrypted to the MXE
+    /// * `true` if `authority` matches the config's authority
+    #[instruction]
+    pub fn rotate_auditor(
+        config: Enc<Mxe, AuditorConfig>,
+        auditor: ArcisX25519Pubkey,
+        new_authority: AccountId,
+        authority: AccountId,
+    ) -> (Enc<Mxe, AuditorConfig>, bool) {
+        let mut state = config.to_arcis();
+        let key = auditor.to_x();
+
+        let rotated = state.authority == authority;
+        if rotated {
+            state.auditor = key;
+            state.authority = new_authority;
+            state.epoch += 1;
+        }
+
+        (config.owner.from_arcis(state), rotated.reveal())
+    }
+
+    /// Copy of `data` if `disclose` is set, otherwise an all-zero transfer
+    /// 
+    /// Used for outputs that always exist but should only carry data in
+    /// some cases, so observers can't tell from the ciphertext which case
+    /// applied.
+    fn disclosed_transfer(data: &TransferAmount, disclose: bool) -> TransferAmount {
+        TransferAmount {
+            amount: if disclose { data.amount } else { 0 },
+            min_balance: if disclose { data.min_balance } else { 0 },
+            fee: if disclose { data.fee } else { 0 },
+            rent_exempt_floor: if disclose { data.rent_exempt_floor } else { 0 },
+            mint: if disclose { data.mint } else { 0 },
+            decimals: if disclose { data.decimals } else { 0 },
+            memo: Memo {
+                words: if disclose { data.memo.words } else { [0; MEMO_WORDS] },
+            },
+            sequence: if disclose { data.sequence } else { 0 },
+        }
+    }
+
+    /// Re-encrypts a transfer from `sender` to the registered auditor
+    /// 
+    /// If the config belongs to another account the output is an all-zero
+    /// transfer.
+    fn audit_copy(
+        config: &AuditorConfig,
+        sender: AccountId,
+        data: &TransferAmount,
+    ) -> Enc<Shared, TransferAmount> {
+        let auditor = ArcisX25519Pubkey::new_from_x(config.auditor);
+
+        Shared::new(auditor).from_arcis(disclosed_transfer(data, config.account == sender))
+    }
+
+    /// Executes a confidential transfer and discloses it to the sender's auditor
+    /// 
+    /// Behaves exactly like `confidential_transfer`, and additionally emits
+    /// the transfer re-encrypted to the auditor registered in `config`. A
+    /// config registered for another account rejects the transfer with
+    /// `TRANSFER_ACCOUNT_MISMATCH`.
+    /// 
+    /// # Returns
+    /// * Updated sender and recipient balances and the sender's policy,
+    ///   re-encrypted to the MXE
+    /// * The recipient's view of the transfer, encrypted to `recipient`
+    /// * The transfer, encrypted to the auditor (all zero on an account
+    ///   mismatch)
+    /// * `TRANSFER_OK` if the transfer was applied, otherwise the rejection reason
+    #[instruction]
+    pub fn confidential_transfer_audited(
+        sender_balance: Enc<Mxe, Balance>,
+        recipient_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        transfer: Enc<Shared, TransferAmount>,
+        recipient: Shared,
+        config: Enc<Mxe, AuditorConfig>,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, Balance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, SpendingPolicy>,
+        Enc<Shared, RecipientAmount>,
+        Enc<Shared, TransferAmount>,
+        u8,
+    ) {
+        let mut sender = sender_balance.to_arcis();
+        let mut credited = recipient_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = transfer.to_arcis();
+        let auditor = config.to_arcis();
+
+        let audit = audit_copy(&auditor, sender.owner, &data);
+        let status = if auditor.account == sender.owner {
+            TRANSFER_OK
+        } else {
+            TRANSFER_ACCOUNT_MISMATCH
+        };
+        let status = apply_transfer(
+            &mut sender,
+            &mut credited,
+            &mut limits,
+            &data,
+            current_slot,
+            status,
+        );
+        let view = recipient_view(&disclosed_transfer(&data, status == TRANSFER_OK));
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient_balance.owner.from_arcis(credited),
+            policy.owner.from_arcis(limits),
+            recipient.from_arcis(view),
+            audit,
+            status.reveal(),
+        )
+    }
+
+    /// Re-encrypts the transfer amount to the recipient and the sender's auditor
+    /// 
+    /// Audited counterpart of `reencrypt_to_recipient`. The sender's
+    /// sequence number is consumed the same way, and a config registered
+    /// for another account is rejected with `TRANSFER_ACCOUNT_MISMATCH`;
+    /// both outputs are all zero unless the status is `TRANSFER_OK`.
+    /// 
+    /// # Returns
+    /// * The sender's balance with its sequence number consumed
+    /// * The recipient's view of the transfer, encrypted to `recipient`
+    /// * The transfer, encrypted to the auditor
+    /// * `TRANSFER_OK`, `TRANSFER_ACCOUNT_MISMATCH` or `TRANSFER_REPLAYED`
+    #[instruction]
+    pub fn reencrypt_to_recipient_audited(
+        sender_balance: Enc<Mxe, Balance>,
+        transfer: Enc<Shared, TransferAmount>,
+        recipient: Shared,
+        config: Enc<Mxe, AuditorConfig>,
+    ) -> (
+        Enc<Mxe, Balance>,
+        Enc<Shared, RecipientAmount>,
+        Enc<Shared, TransferAmount>,
+        u8,
+    ) {
+        let mut sender = sender_balance.to_arcis();
+        let data = transfer.to_arcis();
+        let auditor = config.to_arcis();
+
+        let status = if auditor.account == sender.owner {
+            TRANSFER_OK
+        } else {
+            TRANSFER_ACCOUNT_MISMATCH
+        };
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+        let disclosed = disclosed_transfer(&data, status == TRANSFER_OK);
+        let audit = audit_copy(&auditor, sender.owner, &disclosed);
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient.from_arcis(recipient_view(&disclosed)),
+            audit,
+            status.reveal(),
+        )
+    }
+
+    /// Regulatory reporting threshold held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, ReportingThreshold>`, so the threshold
+    /// is never decrypted outside the MPC cluster. The compliance key is
+    /// stored with it, so reportable transfers can only be disclosed to the
+    /// compliance team that set the threshold.
+    pub struct ReportingThreshold {
+        /// Mint the threshold is denominated in
+        pub mint: MintId,
+        /// Transfers strictly above this amount are reportable
+        pub threshold: u64,
+        /// x-coordinate of the compliance team's x25519 public key (MXE
+        /// state can only hold numeric data)
+        pub compliance_key: BaseField,
+    }
+
+    /// Encrypted threshold chosen by the compliance team
+    pub struct ReportingLimit {
+        pub mint: MintId,
+        pub threshold: u64,
+    }
+
+    /// Stores a reporting threshold supplied by the compliance team
+    /// 
+    /// The on-chain program must only queue this for the compliance
+    /// authority, and only pass the threshold account it created to
+    /// `check_reporting_threshold`.
+    #[instruction]
+    pub fn set_reporting_threshold(
+        mxe: Mxe,
+        threshold: Enc<Shared, ReportingLimit>,
+        compliance_key: ArcisX25519Pubkey,
+    ) -> Enc<Mxe, ReportingThreshold> {
+        let data = threshold.to_arcis();
+
+        mxe.from_arcis(ReportingThreshold {
+            mint: data.mint,
+            threshold: data.threshold,
+            compliance_key: compliance_key.to_x(),
+        })
+    }
+
+    /// Checks whether a transfer must be reported
+    /// 
+    /// Only the reportable bit is revealed. Reportable transfers are also
+    /// re-encrypted to the compliance key stored with the threshold; for
+    /// every other transfer that output is an all-zero transfer, so nothing
+    /// about them is disclosed. Transfers in a different mint than the
+    /// threshold are never reportable under it.
+    /// 
+    /// The revealed bit is a threshold oracle: about 64 queries with chosen
+    /// amounts recover the threshold by binary search. The on-chain program
+    /// must therefore only queue this for the compliance authority that
+    /// owns `threshold`, never for senders, so the threshold can't be
+    /// learned and used to structure transfers just below it.
+    /// 
+    /// # Arguments
+    /// * `transfer` - Encrypted transfer details
+    /// * `threshold` - Encrypted reporting threshold
+    /// 
+    /// # Returns
+    /// * The transfer, encrypted to the compliance key (all zero if not reportable)
+    /// * `true` if the transfer is reportable
+    #[instruction]
+    pub fn check_reporting_threshold(
+        transfer: Enc<Shared, TransferAmount>,
+        threshold: Enc<Mxe, ReportingThreshold>,
+    ) -> (Enc<Shared, TransferAmount>, bool) {
+        let data = transfer.to_arcis();
+        let limit = threshold.to_arcis();
+
+        let reportable = (data.mint == limit.mint) && (data.amount > limit.threshold);
+        let compliance = Shared::new(ArcisX25519Pubkey::new_from_x(limit.compliance_key));
+
+        (
+            compliance.from_arcis(disclosed_transfer(&data, reportable)),
+            reportable.reveal(),
+        )
+    }
+
+    /// Shields public lamports into an MXE-held balance
+    /// 
+    /// The deposited amount and mint are public, since they arrive on-chain
+    /// as a plain token transfer, but the resulting balance stays encrypted.
+    /// Deposits are credited to `pending`, like incoming transfers.
+    /// 
+    /// # Returns
+    /// * The updated balance, re-encrypted to the MXE
+    /// * `true` if the deposit was credited, `false` if the mint doesn't
+    ///   match, the amount is zero, or it would overflow
+    #[instruction]
+    pub fn deposit(
+        balance: Enc<Mxe, Balance>,
+        mint: MintId,
+        amount: u64,
+    ) -> (Enc<Mxe, Balance>, bool) {
+        let mut data = balance.to_arcis();
+
+        let accepted = (data.mint == mint) && (amount > 0) && (data.pending <= u64::MAX - amount);
+        if accepted {
+            data.pending += amount;
+        }
+
+        (balance.owner.from_arcis(data), accepted.reveal())
+    }
+
+    /// Encrypted request to unshield part of a balance
+    pub struct WithdrawRequest {
+        /// The requested amount in base units of the balance's mint
+        pub amount: u64,
+        /// Must equal the balance's current sequence number
+        pub sequence: u64,
+    }
+
+    /// Unshields lamports from an MXE-held balance
+    /// 
+    /// The requested amount is checked against the available balance. Only
+    /// the approved amount is revealed, since the on-chain program has to
+    /// release exactly that many tokens of `mint`; a rejected request
+    /// reveals zero and leaves the balance unchanged. Requests carry the
+    /// balance's sequence number and count against the owner's spending
+    /// policy, like outgoing transfers.
+    /// 
+    /// # Returns
+    /// * The updated balance and policy, re-encrypted to the MXE
+    /// * The approved amount to release on-chain (0 if rejected)
+    /// * `TRANSFER_OK` if approved, otherwise the rejection reason
+    #[instruction]
+    pub fn withdraw(
+        balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        mint: MintId,
+        request: Enc<Shared, WithdrawRequest>,
+        current_slot: u64,
+    ) -> (Enc<Mxe, Balance>, Enc<Mxe, SpendingPolicy>, u64, u8) {
+        let mut data = balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let requested = request.to_arcis();
+
+        let status = if data.mint != mint {
+            TRANSFER_MINT_MISMATCH
+        } else if requested.amount == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if requested.amount > data.available {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            &mut limits,
+            data.owner,
+            mint,
+            requested.amount,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut data.sequence, requested.sequence, status);
+
+        let approved = if status == TRANSFER_OK { requested.amount } else { 0 };
+        data.available -= approved;
+        record_spend(&mut limits, approved, status);
+
+        (
+            balance.owner.from_arcis(data),
+            policy.owner.from_arcis(limits),
+            approved.reveal(),
+            status.reveal(),
+        )
+    }
+
+    /// Confidential transfer locked in escrow until a public slot
+    /// 
+    /// Stored on-chain as `Enc<Mxe, ScheduledTransfer>`. The amount stays
+    /// encrypted from the moment it leaves the sender until it reaches the
+    /// recipient's pending balance.
+    pub struct ScheduledTransfer {
+        /// Escrowed amount in base units of `mint` (0 once released)
+        pub amount: u64,
+        pub mint: MintId,
+        /// Account whose balance the escrow is released to
+        pub recipient: AccountId,
+        /// First slot at which the escrow can be released
+        pub unlock_slot: u64,
+        pub released: bool,
+    }
+
+    /// Debits the sender's available balance into a time-locked escrow
+    /// 
+    /// Runs the sender-side checks of `confidential_transfer`, including the
+    /// sequence number and spending policy. A rejected transfer yields an
+    /// empty escrow that releases nothing.
+    /// 
+    /// # Arguments
+    /// * `sender_balance` - Sender's encrypted balance state
+    /// * `policy` - Sender's spending policy for the balance's mint
+    /// * `transfer` - Encrypted transfer details from the sender
+    /// * `recipient` - Account the escrow can be released to
+    /// * `unlock_slot` - Public slot from which the escrow can be released
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * The updated sender balance and policy and the new escrow, encrypted
+    ///   to the MXE
+    /// * `TRANSFER_OK` if the amount was escrowed, otherwise the rejection reason
+    #[instruction]
+    pub fn schedule_transfer(
+        mxe: Mxe,
+        sender_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        transfer: Enc<Shared, TransferAmount>,
+        recipient: AccountId,
+        unlock_slot: u64,
+        current_slot: u64,
+    ) -> (Enc<Mxe, Balance>, Enc<Mxe, SpendingPolicy>, Enc<Mxe, ScheduledTransfer>, u8) {
+        let mut sender = sender_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = transfer.to_arcis();
+
+        let status = if data.mint != sender.mint {
+            TRANSFER_MINT_MISMATCH
+        } else if data.amount == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if sender.available < data.amount {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            &mut limits,
+            sender.owner,
+            sender.mint,
+            data.amount,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+
+        let escrowed = if status == TRANSFER_OK { data.amount } else { 0 };
+        sender.available -= escrowed;
+        record_spend(&mut limits, escrowed, status);
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            policy.owner.from_arcis(limits),
+            mxe.from_arcis(ScheduledTransfer {
+                amount: escrowed,
+                mint: data.mint,
+                recipient,
+                unlock_slot,
+                released: false,
+            }),
+            status.reveal(),
+        )
+    }
+
+    /// Credits a scheduled transfer to the recipient once it has unlocked
+    /// 
+    /// The escrow is released at most once, only from its unlock slot
+    /// onwards, and only into the pending balance of its recipient. An
+    /// empty escrow, left behind by a rejected `schedule_transfer`, is never
+    /// released. Both states are left unchanged otherwise.
+    /// 
+    /// # Returns
+    /// * The updated escrow and recipient balance, re-encrypted to the MXE
+    /// * `true` if the escrow was released
+    #[instruction]
+    pub fn release_scheduled(
+        scheduled: Enc<Mxe, ScheduledTransfer>,
+        recipient_balance: Enc<Mxe, Balance>,
+        current_slot: u64,
+    ) -> (Enc<Mxe, ScheduledTransfer>, Enc<Mxe, Balance>, bool) {
+        let mut escrow = scheduled.to_arcis();
+        let mut recipient = recipient_balance.to_arcis();
+
+        let releasable = !escrow.released
+            && (escrow.amount > 0)
+            && (current_slot >= escrow.unlock_slot)
+            && (recipient.owner == escrow.recipient)
+            && (recipient.mint == escrow.mint)
+            && (recipient.pending <= u64::MAX - escrow.amount);
+        if releasable {
+            recipient.pending += escrow.amount;
+            escrow.amount = 0;
+            escrow.released = true;
+        }
+
+        (
+            scheduled.owner.from_arcis(escrow),
+            recipient_balance.owner.from_arcis(recipient),
+            releasable.reveal(),
+        )
+    }
+
+    /// Fractional bits of the fixed-point vesting schedule (Q32)
+    const VESTING_FRACTION_BITS: u32 = 32;
+
+    /// Encrypted grant terms, chosen by the grantor
+    pub struct VestingTerms {
+        /// Total grant in base units of the grantor's mint
+        pub total: u64,
+        /// Slots after `start_slot` before anything vests
+        pub cliff: u64,
+        /// Slots after `start_slot` until the grant is fully vested
+        pub duration: u64,
+        /// Must equal the grantor's current sequence number
+        pub sequence: u64,
+    }
+
+    /// Vesting grant held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, VestingGrant>`. The grant is funded
+    /// up front, so its size and schedule stay hidden from everyone but the
+    /// MPC cluster.
+    pub struct VestingGrant {
+        pub total: u64,
+        pub cliff: u64,
+        pub duration: u64,
+        /// Amount already moved to the beneficiary
+        pub claimed: u64,
+        /// Public slot the schedule starts from
+        pub start_slot: u64,
+        pub beneficiary: AccountId,
+        pub mint: MintId,
+    }
+
+    /// Funds a vesting grant from the grantor's available balance
+    /// 
+    /// The cliff must not be longer than the duration, and the duration
+    /// must be non-zero; invalid terms are reported as `TRANSFER_INVALID_TERMS`.
+    /// The total counts against the grantor's spending policy. A rejected
+    /// grant yields an empty grant that never releases anything.
+    /// 
+    /// # Arguments
+    /// * `grantor_balance` - Grantor's encrypted balance state
+    /// * `policy` - Grantor's spending policy for the balance's mint
+    /// * `terms` - Encrypted grant terms from the grantor
+    /// * `beneficiary` - Account the grant vests to
+    /// * `start_slot` - Public slot the schedule starts from
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * The updated grantor balance and policy and the new grant, encrypted
+    ///   to the MXE
+    /// * `TRANSFER_OK` if the grant was funded, otherwise the rejection reason
+    #[instruction]
+    pub fn create_vesting(
+        mxe: Mxe,
+        grantor_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        terms: Enc<Shared, VestingTerms>,
+        beneficiary: AccountId,
+        start_slot: u64,
+        current_slot: u64,
+    ) -> (Enc<Mxe, Balance>, Enc<Mxe, SpendingPolicy>, Enc<Mxe, VestingGrant>, u8) {
+        let mut grantor = grantor_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = terms.to_arcis();
+
+        let status = if data.total == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if (data.duration == 0) || (data.cliff > data.duration) {
+            TRANSFER_INVALID_TERMS
+        } else if grantor.available < data.total {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            &mut limits,
+            grantor.owner,
+            grantor.mint,
+            data.total,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut grantor.sequence, data.sequence, status);
+
+        let funded = if status == TRANSFER_OK { data.total } else { 0 };
+        grantor.available -= funded;
+        record_spend(&mut limits, funded, status);
+
+        (
+            grantor_balance.owner.from_arcis(grantor),
+            policy.owner.from_arcis(limits),
+            mxe.from_arcis(VestingGrant {
+                total: funded,
+                cliff: data.cliff,
+                duration: data.duration,
+                claimed: 0,
+                start_slot,
+                beneficiary,
+                mint: grantor.mint,
+            }),
+            status.reveal(),
+        )
+    }
+
+    /// Amount of `grant` vested at `current_slot`
+    /// 
+    /// Nothing vests before the cliff. After it, the vested amount grows
+    /// linearly from the start slot and reaches `total` at the end of the
+    /// duration. The elapsed fraction is computed in Q32 fixed point and
+    /// rounded down.
+    fn vested_amount(grant: &VestingGrant, current_slot: u64) -> u64 {
+        let elapsed = if current_slot > grant.start_slot {
+            current_slot - grant.start_slot
+        } else {
+            0
+        };
+
+        if elapsed < grant.cliff {
+            0
+        } else if elapsed >= grant.duration {
+            grant.total
+        } else {
+            let fraction = ((elapsed as u128) << VESTING_FRACTION_BITS) / (grant.duration as u128);
+            (((grant.total as u128) * fraction) >> VESTING_FRACTION_BITS) as u64
+        }
+    }
+
+    /// Moves everything vested but not yet claimed to the beneficiary
+    /// 
+    /// The releasable amount is credited to the pending balance of the
+    /// grant's beneficiary. Only whether anything was released is
+    /// revealed, not how much.
+    /// 
+    /// # Returns
+    /// * The updated grant and beneficiary balance, re-encrypted to the MXE
+    /// * `true` if a non-zero amount was released
+    #[instruction]
+    pub fn claim_vested(
+        grant: Enc<Mxe, VestingGrant>,
+        beneficiary_balance: Enc<Mxe, Balance>,
+        current_slot: u64,
+    ) -> (Enc<Mxe, VestingGrant>, Enc<Mxe, Balance>, bool) {
+        let mut state = grant.to_arcis();
+        let mut beneficiary = beneficiary_balance.to_arcis();
+
+        let vested = vested_amount(&state, current_slot);
+        let releasable = if vested > state.claimed { vested - state.claimed } else { 0 };
+
+        let success = (releasable > 0)
+            && (beneficiary.owner == state.beneficiary)
+            && (beneficiary.mint == state.mint)
+            && (beneficiary.pending <= u64::MAX - releasable);
+        if success {
+            beneficiary.pending += releasable;
+            state.claimed += releasable;
+        }
+
+        (
+            grant.owner.from_arcis(state),
+            beneficiary_balance.owner.from_arcis(beneficiary),
+            success.reveal(),
+        )
+    }
+
+    /// Encrypted stream terms, chosen by the sender
+    pub struct StreamTerms {
+        /// Amount streamed to the recipient per slot
+        pub rate_per_slot: u64,
+        /// Amount escrowed up front; the stream never pays out more
+        pub deposit: u64,
+        /// Must equal the sender's current sequence number
+        pub sequence: u64,
+    }
+
+    /// Continuous payment stream held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, PaymentStream>`. The rate, deposit and
+    /// amounts paid out are only ever decrypted inside the MPC cluster.
+    pub struct PaymentStream {
+        pub rate_per_slot: u64,
+        /// Public slot the stream starts accruing from
+        pub start_slot: u64,
+        /// Escrowed amount, in base units of `mint`
+        pub deposit: u64,
+        /// Amount already paid out to the recipient
+        pub withdrawn: u64,
+        /// Cleared once the stream is cancelled and settled
+        pub active: bool,
+        pub sender: AccountId,
+        pub recipient: AccountId,
+        pub mint: MintId,
+    }
+
+    /// Escrows a deposit from the sender's available balance and starts a stream
+    /// 
+    /// A rejected stream is created inactive with nothing escrowed.
+    /// A zero deposit is reported as `TRANSFER_ZERO_AMOUNT` and a zero rate
+    /// as `TRANSFER_INVALID_TERMS`. The whole deposit counts against the
+    /// sender's spending policy.
+    /// 
+    /// # Returns
+    /// * The updated sender balance and policy and the new stream, encrypted
+    ///   to the MXE
+    /// * `TRANSFER_OK` if the stream was funded, otherwise the rejection reason
+    #[instruction]
+    pub fn create_stream(
+        mxe: Mxe,
+        sender_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        terms: Enc<Shared, StreamTerms>,
+        recipient: AccountId,
+        start_slot: u64,
+        current_slot: u64,
+    ) -> (Enc<Mxe, Balance>, Enc<Mxe, SpendingPolicy>, Enc<Mxe, PaymentStream>, u8) {
+        let mut sender = sender_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = terms.to_arcis();
+
+        let status = if data.deposit == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if data.rate_per_slot == 0 {
+            TRANSFER_INVALID_TERMS
+        } else if sender.available < data.deposit {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            &mut limits,
+            sender.owner,
+            sender.mint,
+            data.deposit,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+
+        let funded = status == TRANSFER_OK;
+        let deposit = if funded { data.deposit } else { 0 };
+        sender.available -= deposit;
+        record_spend(&mut limits, deposit, status);
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            policy.owner.from_arcis(limits),
+            mxe.from_arcis(PaymentStream {
+                rate_per_slot: data.rate_per_slot,
+                start_slot,
+                deposit,
+                withdrawn: 0,
+                active: funded,
+                sender: sender.owner,
+                recipient,
+                mint: sender.mint,
+            }),
+            status.reveal(),
+        )
+    }
+
+    /// Total amount `stream` has accrued to the recipient by `current_slot`,
+    /// capped at the deposit
+    fn streamed_amount(stream: &PaymentStream, current_slot: u64) -> u64 {
+        let elapsed = if current_slot > stream.start_slot {
+            current_slot - stream.start_slot
+        } else {
+            0
+        };
+        let accrued = (stream.rate_per_slot as u128) * (elapsed as u128);
+
+        if accrued > (stream.deposit as u128) {
+            stream.deposit
+        } else {
+            accrued as u64
+        }
+    }
+
+    /// Pays the recipient everything the stream has accrued so far
+    /// 
+    /// The amount is credited to the recipient's pending balance. Only
+    /// whether anything was paid out is revealed.
+    /// 
+    /// # Returns
+    /// * The updated stream and recipient balance, re-encrypted to the MXE
+    /// * `true` if a non-zero amount was paid out
+    #[instruction]
+    pub fn withdraw_stream(
+        stream: Enc<Mxe, PaymentStream>,
+        recipient_balance: Enc<Mxe, Balance>,
+        current_slot: u64,
+    ) -> (Enc<Mxe, PaymentStream>, Enc<Mxe, Balance>, bool) {
+        let mut state = stream.to_arcis();
+        let mut recipient = recipient_balance.to_arcis();
+
+        let streamed = streamed_amount(&state, current_slot);
+        let owed = if streamed > state.withdrawn { streamed - state.withdrawn } else { 0 };
+
+        let success = state.active
+            && (owed > 0)
+            && (recipient.owner == state.recipient)
+            && (recipient.mint == state.mint)
+            && (recipient.pending <= u64::MAX - owed);
+        if success {
+            recipient.pending += owed;
+            state.withdrawn += owed;
+        }
+
+        (
+            stream.owner.from_arcis(state),
+            recipient_balance.owner.from_arcis(recipient),
+            success.reveal(),
+        )
+    }
+
+    /// Stops a stream and settles both sides
+    /// 
+    /// Only the stream's sender can cancel. What has accrued but not been
+    /// withdrawn goes to the recipient, and the rest of the deposit is
+    /// refunded to the sender; both are credited to pending balances. All
+    /// three states are left unchanged if either side can't be credited.
+    /// 
+    /// # Returns
+    /// * Updated stream, sender and recipient balances, re-encrypted to the MXE
+    /// * `true` if the stream was cancelled
+    #[instruction]
+    pub fn cancel_stream(
+        stream: Enc<Mxe, PaymentStream>,
+        sender_balance: Enc<Mxe, Balance>,
+        recipient_balance: Enc<Mxe, Balance>,
+        current_slot: u64,
+        sender: AccountId,
+    ) -> (Enc<Mxe, PaymentStream>, Enc<Mxe, Balance>, Enc<Mxe, Balance>, bool) {
+        let mut state = stream.to_arcis();
+        let mut sender_state = sender_balance.to_arcis();
+        let mut recipient = recipient_balance.to_arcis();
+
+        // Never settle below what was already paid out, so the refund can't
+        // return funds the recipient has withdrawn
+        let streamed = streamed_amount(&state, current_slot);
+        let settled = if streamed > state.withdrawn { streamed } else { state.withdrawn };
+        let owed = settled - state.withdrawn;
+        let refund = state.deposit - settled;
+
+        let success = state.active
+            && (state.sender == sender)
+            && (sender_state.owner == state.sender)
+            && (recipient.owner == state.recipient)
+            && (sender_state.mint == state.mint)
+            && (recipient.mint == state.mint)
+            && (sender_state.pending <= u64::MAX - refund)
+            && (recipient.pending <= u64::MAX - owed);
+        if success {
+            recipient.pending += owed;
+            sender_state.pending += refund;
+            state.withdrawn = settled;
+            state.deposit = settled;
+            state.active = false;
+        }
+
+        (
+            stream.owner.from_arcis(state),
+            sender_balance.owner.from_arcis(sender_state),
+            recipient_balance.owner.from_arcis(recipient),
+            success.reveal(),
+        )
+    }
+
+    /// Delegated spending allowance held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, Allowance>` in an account derived from
+    /// `owner` and `spender`. Both are also kept inside the encrypted state
+    /// and checked against the caller and the balance being debited, so an
+    /// allowance can't be replayed against another owner or spender.
+    pub struct Allowance {
+        pub owner: AccountId,
+        pub spender: AccountId,
+        pub mint: MintId,
+        /// Amount the spender can still pull, in base units of `mint`
+        pub amount: u64,
+        /// Sequence number the spender's next pull must carry
+        pub sequence: u64,
+    }
+
+    /// Creates an empty allowance for (owner, spender)
+    /// 
+    /// The allowance account is opened once and then only updated, so its
+    /// sequence number keeps increasing across approvals and revocations.
+    #[instruction]
+    pub fn open_allowance(
+        mxe: Mxe,
+        owner: AccountId,
+        spender: AccountId,
+        mint: MintId,
+    ) -> Enc<Mxe, Allowance> {
+        mxe.from_arcis(Allowance {
+            owner,
+            spender,
+            mint,
+            amount: 0,
+            sequence: 0,
+        })
+    }
+
+    /// Encrypted allowance amount chosen by the owner
+    pub struct AllowanceRequest {
+        pub amount: u64,
+    }
+
+    /// Approves the allowance's spender to pull up to the requested amount
+    /// 
+    /// The request is checked against the owner's encrypted balance, the
+    /// same way `check_sufficient_balance` does; a balance belonging to
+    /// anyone else is rejected. A rejected request leaves the allowance
+    /// unchanged.
+    /// 
+    /// # Returns
+    /// * The updated allowance, re-encrypted to the MXE
+    /// * `true` if the allowance was granted
+    #[instruction]
+    pub fn approve(
+        allowance: Enc<Mxe, Allowance>,
+        owner_balance: Enc<Mxe, Balance>,
+        request: Enc<Shared, AllowanceRequest>,
+        owner: AccountId,
+    ) -> (Enc<Mxe, Allowance>, bool) {
+        let mut state = allowance.to_arcis();
+        let balance = owner_balance.to_arcis();
+        let data = request.to_arcis();
+
+        let granted = (state.owner == owner)
+            && (balance.owner == state.owner)
+            && (state.mint == balance.mint)
+            && (data.amount > 0)
+            && (balance.available >= data.amount);
+        if granted {
+            state.amount = data.amount;
+        }
+
+        (allowance.owner.from_arcis(state), granted.reveal())
+    }
+
+    /// Revokes an allowance by setting its remaining amount to zero
+    /// 
+    /// # Returns
+    /// * The updated allowance, re-encrypted to the MXE
+    /// * `true` if `owner` matches the allowance's owner
+    #[instruction]
+    pub fn revoke(allowance: Enc<Mxe, Allowance>, owner: AccountId) -> (Enc<Mxe, Allowance>, bool) {
+        let mut state = allowance.to_arcis();
+
+        let revoked = state.owner == owner;
+        if revoked {
+            state.amount = 0;
+        }
+
+        (allowance.owner.from_arcis(state), revoked.reveal())
+    }
+
+    /// Pulls funds from the owner's balance on behalf of a spender
+    /// 
+    /// `owner_balance` must belong to the allowance's owner, and the amount
+    /// must fit within the allowance and the owner's available balance; on
+    /// success it moves from the owner to the pending balance of
+    /// `recipient_balance` and is deducted from the allowance.
+    /// `transfer.sequence` must match the allowance's sequence number, which
+    /// only the spender consumes. The amount also counts against the
+    /// owner's spending policy.
+    /// 
+    /// Apart from `TRANSFER_REPLAYED`, every rejection is reported as
+    /// `TRANSFER_ALLOWANCE_REJECTED`, so the spender never learns whether
+    /// it was the allowance, the owner's balance or the owner's limits that
+    /// fell short.
+    /// 
+    /// # Arguments
+    /// * `allowance` - Encrypted allowance for (owner, spender)
+    /// * `owner_balance` - Owner's encrypted balance state
+    /// * `policy` - Owner's spending policy for the balance's mint
+    /// * `recipient_balance` - Encrypted balance credited with the funds
+    /// * `transfer` - Encrypted transfer details from the spender
+    /// * `spender` - Public identity of the caller
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * Updated allowance, owner balance and policy, and recipient balance,
+    ///   re-encrypted to the MXE
+    /// * `TRANSFER_OK`, `TRANSFER_REPLAYED` or `TRANSFER_ALLOWANCE_REJECTED`
+    #[instruction]
+    pub fn spend_allowance(
+        allowance: Enc<Mxe, Allowance>,
+        owner_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        recipient_balance: Enc<Mxe, Balance>,
+        transfer: Enc<Shared, TransferAmount>,
+        spender: AccountId,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, Allowance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, SpendingPolicy>,
+        Enc<Mxe, Balance>,
+        u8,
+    ) {
+        let mut state = allowance.to_arcis();
+        let mut owner = owner_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let mut recipient = recipient_balance.to_arcis();
+        let data = transfer.to_arcis();
+
+        let allowed = (state.spender == spender)
+            && (owner.owner == state.owner)
+            && (state.mint == data.mint)
+            && (data.amount <= state.amount);
+        let status = ledger_status(&owner, &recipient, &data);
+        let status = limit_status(
+            &mut limits,
+            owner.owner,
+            owner.mint,
+            data.amount,
+            current_slot,
+            status,
+        );
+        let status = if allowed && (status == TRANSFER_OK) {
+            TRANSFER_OK
+        } else {
+            TRANSFER_ALLOWANCE_REJECTED
+        };
+        let status = consume_sequence(&mut state.sequence, data.sequence, status);
+
+        if status == TRANSFER_OK {
+            owner.available -= data.amount;
+            recipient.pending += data.amount;
+            state.amount -= data.amount;
+        }
+        record_spend(&mut limits, data.amount, status);
+
+        (
+            allowance.owner.from_arcis(state),
+            owner_balance.owner.from_arcis(owner),
+            policy.owner.from_arcis(limits),
+            recipient_balance.owner.from_arcis(recipient),
+            status.reveal(),
+        )
+    }
+
+    /// Encrypted subscription price, chosen by the merchant
+    pub struct SubscriptionPrice {
+        /// Amount charged per period, in base units of the subscription's mint
+        pub price: u64,
+    }
+
+    /// Recurring subscription held by the MXE
+    /// 
+    /// Stored on-chain as `Enc<Mxe, Subscription>`. Charges are pulled
+    /// through the subscriber's `Allowance` to the merchant, so the
+    /// subscriber caps the total the merchant can ever take.
+    pub struct Subscription {
+        pub price: u64,
+        /// Billing period length in slots
+        pub period: u64,
+        /// Public slot the first period starts at
+        pub start_slot: u64,
+        /// Index of the first period that hasn't been charged yet
+        pub next_period: u64,
+        /// Cleared when the subscriber cancels
+        pub active: bool,
+        pub subscriber: AccountId,
+        pub merchant: AccountId,
+        pub mint: MintId,
+    }
+
+    /// Key a subscriber's billing records are encrypted to
+    /// 
+    /// Stored on-chain as `Enc<Mxe, BillingKey>` in an account derived from
+    /// the subscriber. The subscriber registers it, rather than the
+    /// merchant who registers the subscription, so billing history can't
+    /// be redirected to a key of the merchant's choosing.
+    pub struct BillingKey {
+        pub subscriber: AccountId,
+        /// x-coordinate of the subscriber's x25519 public key (MXE state can
+        /// only hold numeric data)
+        pub key: BaseField,
+    }
+
+    /// Registers the key the subscriber's billing records are encrypted to
+    /// 
+    /// `subscriber` is the public identity of the caller; the on-chain
+    /// program must only queue this for the subscriber.
+    #[instruction]
+    pub fn register_billing_key(
+        mxe: Mxe,
+        subscriber: AccountId,
+        key: ArcisX25519Pubkey,
+    ) -> Enc<Mxe, BillingKey> {
+        mxe.from_arcis(BillingKey {
+            subscriber,
+            key: key.to_x(),
+        })
+    }
+
+    /// Billing record for a single charge, encrypted to the subscriber
+    /// 
+    /// Rejected charges produce an all-zero record, so the record is only
+    /// meaningful alongside the revealed success flag.
+    pub struct BillingRecord {
+        pub amount: u64,
+        /// Index of the period that was charged
+        pub period: u64,
+        pub merchant: AccountId,
+    }
+
+    /// Registers a subscription with an encrypted price
+    /// 
+    /// A zero price or period yields an inactive subscription that can
+    /// never be charged.
+    #[instruction]
+    pub fn create_subscription(
+        mxe: Mxe,
+        price: Enc<Shared, SubscriptionPrice>,
+        period: u64,
+        start_slot: u64,
+        subscriber: AccountId,
+        merchant: AccountId,
+        mint: MintId,
+    ) -> Enc<Mxe, Subscription> {
+        let data = price.to_arcis();
+
+        mxe.from_arcis(Subscription {
+            price: data.price,
+            period,
+            start_slot,
+            next_period: 0,
+            active: (data.price > 0) && (period > 0),
+            subscriber,
+            merchant,
+            mint,
+        })
+    }
+
+    /// Charges the subscriber for the current period
+    /// 
+    /// The price is pulled through the subscriber's allowance to the
+    /// merchant, from the subscriber's available balance to the merchant's
+    /// pending balance. The circuit refuses to charge any period twice, so
+    /// replaying the charge doesn't need the allowance's sequence number.
+    /// Charges count against the subscriber's spending policy. Periods that
+    /// were skipped are not charged retroactively.
+    /// 
+    /// The billing record is encrypted to the key in `billing_key`, which
+    /// must be the subscriber's own registration; a charge with anyone
+    /// else's is rejected.
+    /// 
+    /// # Arguments
+    /// * `subscription` - The encrypted subscription state
+    /// * `allowance` - Subscriber's allowance for the merchant
+    /// * `subscriber_balance` - Subscriber's encrypted balance state
+    /// * `policy` - Subscriber's spending policy for the subscription's mint
+    /// * `merchant_balance` - Merchant's encrypted balance state
+    /// * `billing_key` - Key registered by the subscriber for billing records
+    /// * `current_slot` - Current Solana slot
+    /// 
+    /// # Returns
+    /// * Updated subscription, allowance, balances and policy, re-encrypted
+    ///   to the MXE
+    /// * The billing record, encrypted to the subscriber
+    /// * `true` if the subscriber was charged
+    #[instruction]
+    pub fn charge_subscription(
+        subscription: Enc<Mxe, Subscription>,
+        allowance: Enc<Mxe, Allowance>,
+        subscriber_balance: Enc<Mxe, Balance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        merchant_balance: Enc<Mxe, Balance>,
+        billing_key: Enc<Mxe, BillingKey>,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, Subscription>,
+        Enc<Mxe, Allowance>,
+        Enc<Mxe, Balance>,
+        Enc<Mxe, SpendingPolicy>,
+        Enc<Mxe, Balance>,
+        Enc<Shared, BillingRecord>,
+        bool,
+    ) {
+        let mut state = subscription.to_arcis();
+        let mut approved = allowance.to_arcis();
+        let mut subscriber = subscriber_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let mut merchant = merchant_balance.to_arcis();
+        let key = billing_key.to_arcis();
+
+        let started = current_slot >= state.start_slot;
+        let period_length = if state.period == 0 { 1 } else { state.period };
+        let current_period = if started {
+            (current_slot - state.start_slot) / period_length
+        } else {
+            0
+        };
+
+        let chargeable = state.active
+            && (key.subscriber == state.subscriber)
+            && started
+            && (current_period >= state.next_period)
+            && (approved.owner == state.subscriber)
+            && (approved.spender == state.merchant)
+            && (approved.mint == state.mint)
+            && (state.price <= approved.amount)
+            && (subscriber.owner == state.subscriber)
+            && (merchant.owner == state.merchant)
+            && (subscriber.mint == state.mint)
+            && (merchant.mint == state.mint)
+            && (subscriber.available >= state.price)
+            && (merchant.pending <= u64::MAX - state.price);
+        let status = if chargeable { TRANSFER_OK } else { TRANSFER_ALLOWANCE_REJECTED };
+        let status = limit_status(
+            &mut limits,
+            state.subscriber,
+            state.mint,
+            state.price,
+            current_slot,
+            status,
+        );
+
+        let charged = status == TRANSFER_OK;
+        if charged {
+            subscriber.available -= state.price;
+            merchant.pending += state.price;
+            approved.amount -= state.price;
+            state.next_period = current_period + 1;
+        }
+        record_spend(&mut limits, state.price, status);
+
+        let record = BillingRecord {
+            amount: if charged { state.price } else { 0 },
+            period: if charged { current_period } else { 0 },
+            merchant: if charged { state.merchant } else { 0 },
+        };
+        let billing = Shared::new(ArcisX25519Pubkey::new_from_x(key.key)).from_arcis(record);
+
+        (
+            subscription.owner.from_arcis(state),
+            allowance.owner.from_arcis(approved),
+            subscriber_balance.owner.from_arcis(subscriber),
+            policy.owner.from_arcis(limits),
+            merchant_balance.owner.from_arcis(merchant),
+            billing,
+            charged.reveal(),
+        )
+    }
+
+    /// Cancels a subscription; only the subscriber can cancel
+    /// 
+    /// # Returns
+    /// * The updated subscription, re-encrypted to the MXE
+    /// * `true` if the subscription was cancelled
+    #[instruction]
+    pub fn cancel_subscription(
+        subscription: Enc<Mxe, Subscription>,
+        subscriber: AccountId,
+    ) -> (Enc<Mxe, Subscription>, bool) {
+        let mut state = subscription.to_arcis();
+
+        let cancelled = state.active && (state.subscriber == subscriber);
+        if cancelled {
+            state.active = false;
+        }
+
+        (subscription.owner.from_arcis(state), cancelled.reveal())
+    }
+
+    /// Number of token slots in a `MultiMintBalance`
+    pub const MAX_MINTS_PER_BALANCE: usize = 4;
+
+    /// Balances of several mints held in one MXE state
+    /// 
+    /// Each mint occupies at most one slot. A slot is claimed the first time
+    /// the account receives that mint and is never released, so the set of
+    /// mints an account holds only changes inside the MPC cluster.
+    /// 
+    /// Like `Balance`, credits land in `pending` and only the owner can move
+    /// them into `available` with `apply_pending_multi_mint`, so incoming
+    /// transfers can't race the owner's outgoing ones.
+    pub struct MultiMintBalance {
+        pub mints: [MintId; MAX_MINTS_PER_BALANCE],
+        /// Spendable amount of each mint
+        pub available: [u64; MAX_MINTS_PER_BALANCE],
+        /// Received but not yet applied amount of each mint
+        pub pending: [u64; MAX_MINTS_PER_BALANCE],
+        pub in_use: [bool; MAX_MINTS_PER_BALANCE],
+        /// Account allowed to apply pending credits
+        pub owner: AccountId,
+        /// Sequence number the next outgoing transfer must carry
+        pub sequence: u64,
+    }
+
+    /// Creates an empty MXE-held multi-mint balance for a new account
+    #[instruction]
+    pub fn open_multi_mint_balance(mxe: Mxe, owner: AccountId) -> Enc<Mxe, MultiMintBalance> {
+        mxe.from_arcis(MultiMintBalance {
+            mints: [0; MAX_MINTS_PER_BALANCE],
+            available: [0; MAX_MINTS_PER_BALANCE],
+            pending: [0; MAX_MINTS_PER_BALANCE],
+            in_use: [false; MAX_MINTS_PER_BALANCE],
+            owner,
+            sequence: 0,
+        })
+    }
+
+    /// Moves received credits of every mint from `pending` into `available`
+    /// 
+    /// Same as `apply_pending_balance`; the balance is left unchanged if
+    /// any slot would overflow.
+    /// 
+    /// # Returns
+    /// * The updated balance, re-encrypted to the MXE
+    /// * `true` if the pending credits were applied
+    #[instruction]
+    pub fn apply_pending_multi_mint(
+        balance: Enc<Mxe, MultiMintBalance>,
+        owner: AccountId,
+    ) -> (Enc<Mxe, MultiMintBalance>, bool) {
+        let mut data = balance.to_arcis();
+
+        let mut fits = true;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if data.available[i] > u64::MAX - data.pending[i] {
+                fits = false;
+            }
+        }
+
+        let applied = (data.owner == owner) && fits;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if applied {
+                data.available[i] += data.pending[i];
+                data.pending[i] = 0;
+            }
+        }
+
+        (balance.owner.from_arcis(data), applied.reveal())
+    }
+
+    /// Re-encrypts a multi-mint balance's sequence number to its owner
+    /// 
+    /// Same as `read_sequence`; the on-chain program must only queue this
+    /// for the account owner.
+    #[instruction]
+    pub fn read_multi_mint_sequence(
+        balance: Enc<Mxe, MultiMintBalance>,
+        owner: Shared,
+    ) -> Enc<Shared, u64> {
+        let data = balance.to_arcis();
+
+        owner.from_arcis(data.sequence)
+    }
+
+    /// Available amount of `mint` in `balance` (0 if it doesn't hold the mint)
+    fn mint_funds(balance: &MultiMintBalance, mint: MintId) -> u64 {
+        let mut funds = 0;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if balance.in_use[i] && (balance.mints[i] == mint) {
+                funds = balance.available[i];
+            }
+        }
+
+        funds
+    }
+
+    /// Whether the pending side of `balance` can be credited with `amount`
+    /// of `mint`, either in its existing slot for the mint or in a free slot
+    fn can_credit_mint(balance: &MultiMintBalance, mint: MintId, amount: u64) -> bool {
+        let mut holds = false;
+        let mut fits = false;
+        let mut has_free = false;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if balance.in_use[i] && (balance.mints[i] == mint) {
+                holds = true;
+                fits = balance.pending[i] <= u64::MAX - amount;
+            }
+            if !balance.in_use[i] {
+                has_free = true;
+            }
+        }
+
+        fits || (!holds && has_free)
+    }
+
+    /// Credits `amount` of `mint` to the pending side if `apply` is set
+    /// 
+    /// Uses the existing slot for the mint, or claims the first free slot if
+    /// `balance` doesn't hold the mint yet. Callers check `can_credit_mint`.
+    fn credit_mint(balance: &mut MultiMintBalance, mint: MintId, amount: u64, apply: bool) {
+        let mut holds = false;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if balance.in_use[i] && (balance.mints[i] == mint) {
+                holds = true;
+            }
+        }
+
+        let mut slot_claimed = false;
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            let mint_slot = balance.in_use[i] && (balance.mints[i] == mint);
+            let free_slot = !holds && !balance.in_use[i] && !slot_claimed;
+
+            if apply && (mint_slot || free_slot) {
+                balance.mints[i] = mint;
+                balance.pending[i] += amount;
+                balance.in_use[i] = true;
+            }
+            if free_slot {
+                slot_claimed = true;
+            }
+        }
+    }
+
+    /// Debits `amount` of `mint` from the available side if `apply` is set;
+    /// callers check `mint_funds`
+    fn debit_mint(balance: &mut MultiMintBalance, mint: MintId, amount: u64, apply: bool) {
+        for i in 0..MAX_MINTS_PER_BALANCE {
+            if apply && balance.in_use[i] && (balance.mints[i] == mint) {
+                balance.available[i] -= amount;
+            }
+        }
+    }
+
+    /// Moves `data.amount` of `data.mint` between two multi-mint balances
+    /// 
+    /// The sender must hold the mint with enough available funds. The
+    /// recipient's pending amount is credited in its existing slot for the
+    /// mint, or in its first free slot if it doesn't hold the mint yet. A
+    /// transfer to oneself is rejected, as in `ledger_status`. Both balances are left untouched if
+    /// either side can't take part or the sender's policy for the mint
+    /// rejects the amount; the sender's sequence number is consumed
+    /// whenever it matches, as in `apply_transfer`.
+    fn apply_multi_mint_transfer(
+        sender: &mut MultiMintBalance,
+        recipient: &mut MultiMintBalance,
+        policy: &mut SpendingPolicy,
+        data: &TransferAmount,
+        current_slot: u64,
+    ) -> u8 {
+        let status = if sender.owner == recipient.owner {
+            TRANSFER_SELF_TRANSFER
+        } else if data.amount == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if mint_funds(sender, data.mint) < data.amount {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else if !can_credit_mint(recipient, data.mint, data.amount) {
+            TRANSFER_RECIPIENT_OVERFLOW
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            policy,
+            sender.owner,
+            data.mint,
+            data.amount,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut sender.sequence, data.sequence, status);
+        let is_valid = status == TRANSFER_OK;
+
+        debit_mint(sender, data.mint, data.amount, is_valid);
+        credit_mint(recipient, data.mint, data.amount, is_valid);
+        record_spend(policy, data.amount, status);
+
+        status
+    }
+
+    /// Shields public tokens of `mint` into a multi-mint balance
+    /// 
+    /// Same as `deposit`: the amount is credited to the pending side. A
+    /// balance with no slot for `mint` and no free slot rejects the deposit.
+    /// 
+    /// # Returns
+    /// * The updated balance, re-encrypted to the MXE
+    /// * `true` if the deposit was credited
+    #[instruction]
+    pub fn deposit_multi_mint(
+        balance: Enc<Mxe, MultiMintBalance>,
+        mint: MintId,
+        amount: u64,
+    ) -> (Enc<Mxe, MultiMintBalance>, bool) {
+        let mut data = balance.to_arcis();
+
+        let accepted = (amount > 0) && can_credit_mint(&data, mint, amount);
+        credit_mint(&mut data, mint, amount, accepted);
+
+        (balance.owner.from_arcis(data), accepted.reveal())
+    }
+
+    /// Unshields tokens of `mint` from a multi-mint balance
+    /// 
+    /// Same as `withdraw`: only the approved amount is revealed, and the
+    /// request must carry the balance's sequence number and pass the
+    /// owner's spending policy for `mint`.
+    /// 
+    /// # Returns
+    /// * The updated balance and policy, re-encrypted to the MXE
+    /// * The approved amount to release on-chain (0 if rejected)
+    /// * `TRANSFER_OK` if approved, otherwise the rejection reason
+    #[instruction]
+    pub fn withdraw_multi_mint(
+        balance: Enc<Mxe, MultiMintBalance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        mint: MintId,
+        request: Enc<Shared, WithdrawRequest>,
+        current_slot: u64,
+    ) -> (Enc<Mxe, MultiMintBalance>, Enc<Mxe, SpendingPolicy>, u64, u8) {
+        let mut data = balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let requested = request.to_arcis();
+
+        let status = if requested.amount == 0 {
+            TRANSFER_ZERO_AMOUNT
+        } else if mint_funds(&data, mint) < requested.amount {
+            TRANSFER_INSUFFICIENT_FUNDS
+        } else {
+            TRANSFER_OK
+        };
+        let status = limit_status(
+            &mut limits,
+            data.owner,
+            mint,
+            requested.amount,
+            current_slot,
+            status,
+        );
+        let status = consume_sequence(&mut data.sequence, requested.sequence, status);
+
+        let approved = if status == TRANSFER_OK { requested.amount } else { 0 };
+        debit_mint(&mut data, mint, approved, status == TRANSFER_OK);
+        record_spend(&mut limits, approved, status);
+
+        (
+            balance.owner.from_arcis(data),
+            policy.owner.from_arcis(limits),
+            approved.reveal(),
+            status.reveal(),
+        )
+    }
+
+    /// Executes a confidential transfer between two multi-mint balances
+    /// 
+    /// Same as `confidential_transfer`, but the mint slot to debit and
+    /// credit is selected inside the MPC cluster from `transfer.mint`.
+    /// 
+    /// The sender's spending policy for `transfer.mint` must be passed in,
+    /// and a policy for any other mint rejects the transfer with
+    /// `TRANSFER_ACCOUNT_MISMATCH`. The policy account is public, so the
+    /// mint only stays hidden if the sender's policy accounts can't be told
+    /// apart on-chain.
+    /// 
+    /// A recipient with no free slot for a new mint is reported as
+    /// `TRANSFER_RECIPIENT_OVERFLOW`.
+    /// 
+    /// # Returns
+    /// * Updated sender and recipient balances and the sender's policy,
+    ///   re-encrypted to the MXE
+    /// * `TRANSFER_OK` if the transfer was applied, otherwise the rejection reason
+    #[instruction]
+    pub fn multi_mint_transfer(
+        sender_balance: Enc<Mxe, MultiMintBalance>,
+        recipient_balance: Enc<Mxe, MultiMintBalance>,
+        policy: Enc<Mxe, SpendingPolicy>,
+        transfer: Enc<Shared, TransferAmount>,
+        current_slot: u64,
+    ) -> (
+        Enc<Mxe, MultiMintBalance>,
+        Enc<Mxe, MultiMintBalance>,
+        Enc<Mxe, SpendingPolicy>,
+        u8,
+    ) {
+        let mut sender = sender_balance.to_arcis();
+        let mut recipient = recipient_balance.to_arcis();
+        let mut limits = policy.to_arcis();
+        let data = transfer.to_arcis();
+
+        let status = apply_multi_mint_transfer(
+            &mut sender,
+            &mut recipient,
+            &mut limits,
+            &data,
+            current_slot,
+        );
+
+        (
+            sender_balance.owner.from_arcis(sender),
+            recipient_balance.owner.from_arcis(recipient),
+            policy.owner.from_arcis(limits),
+            status.reveal(),
+        )
+    }
+
+    /// Private comparison for gaming/auctions
+    /// 
+    /// Compares two hidden values and returns which is larger
+    /// without revealing the actual values.
+    pub struct HiddenComparison {
+        pub value_a: u64,
+        pub value_b: u64,
+    }
+
+    /// Compares two values: 0 = equal, 1 = a > b, 2 = b > a
+    fn compare_values(value_a: u64, value_b: u64) -> u8 {
+        if value_a == value_b {
+            0u8
+        } else if value_a > value_b {
+            1u8
+        } else {
+            2u8
+        }
+    }
+
+    #[instruction]
+    pub fn compare_hidden_values(comparison: Enc<Shared, HiddenComparison>) -> u8 {
+        let data = comparison.to_arcis();
+        
+        // Return: 0 = equal, 1 = a > b, 2 = b > a
+        let result = compare_values(data.value_a, data.value_b);
+        
+        result.reveal()
+    }
+
+    /// One party's hidden value for a two-party comparison
+    pub struct HiddenValue {
+        pub value: u64,
+    }
+
+    /// Two-party form of `compare_hidden_values`
+    /// 
+    /// Each value is encrypted by its own party under its own x25519 key,
+    /// so neither party ever learns the other's value. Returns the same
+    /// 0 = equal, 1 = a > b, 2 = b > a result.
+    #[instruction]
+    pub fn compare_hidden_values_two_party(
+        value_a: Enc<Shared, HiddenValue>,
+        value_b: Enc<Shared, HiddenValue>,
+    ) -> u8 {
+        let a = value_a.to_arcis();
+        let b = value_b.to_arcis();
+
+        compare_values(a.value, b.value).reveal()
+    }
+
+    /// Number of battlefields in a Shadow Duel
+    pub const DUEL_BATTLEFIELDS: usize = 3;
+
+    /// Power each player distributes across the battlefields
+    pub const DUEL_BUDGET: u8 = 10;
+
+    // Duel winner codes (stable ABI, revealed in `DuelOutcome::winner`)
+
+    /// Both players scored the same (no longer produced, since the duel
+    /// resolvers always break ties)
+    pub const DUEL_DRAW: u8 = 0;
+    /// The creator won, on score or on a tiebreak
+    pub const DUEL_CREATOR_WINS: u8 = 1;
+    /// The opponent won, on score or on a tiebreak
+    pub const DUEL_OPPONENT_WINS: u8 = 2;
+    /// The creator's allocation was invalid, so the opponent wins
+    pub const DUEL_CREATOR_FORFEIT: u8 = 3;
+    /// The opponent's allocation was invalid, so the creator wins
+    pub const DUEL_OPPONENT_FORFEIT: u8 = 4;
+    /// Both allocations were invalid
+    pub const DUEL_BOTH_FORFEIT: u8 = 5;
+    /// Both allocations are valid and rounds are still being revealed
+    pub const DUEL_IN_PROGRESS: u8 = 6;
+
+    // Tiebreak rules (revealed in `DuelOutcome::tiebreak`)
+
+    /// Decided on rounds won, or by forfeit
+    pub const DUEL_TIEBREAK_NONE: u8 = 0;
+    /// Decided on total power in the rounds each player won
+    pub const DUEL_TIEBREAK_WON_POWER: u8 = 1;
+    /// Decided by a coin flip generated inside the MPC cluster
+    pub const DUEL_TIEBREAK_RANDOM: u8 = 2;
+
+    /// A player's secret Shadow Duel allocation
+    pub struct DuelAllocation {
+        /// Power committed to each battlefield
+        pub power: [u8; DUEL_BATTLEFIELDS],
+    }
+
+    /// Publicly revealed result of a Shadow Duel
+    pub struct DuelOutcome {
+        /// One of the `DUEL_*` winner codes
+        pub winner: u8,
+        /// Per-round result: 0 = tie, 1 = creator won, 2 = opponent won
+        /// (all zero if either player forfeited)
+        pub rounds: [u8; DUEL_BATTLEFIELDS],
+        /// Which `DUEL_TIEBREAK_*` rule decided the duel
+        pub tiebreak: u8,
+    }
+
+    /// Maximum number of battlefields in a configurable duel
+    pub const DUEL_MAX_BATTLEFIELDS: usize = 8;
+
+    /// Public parameters of a generalized Colonel Blotto duel
+    /// 
+    /// Chosen by the game creator and stored in plaintext with the game.
+    /// The on-chain program should reject configs with zero or more than
+    /// `DUEL_MAX_BATTLEFIELDS` battlefields; the circuit itself caps the
+    /// count at `DUEL_MAX_BATTLEFIELDS` and treats every allocation as
+    /// invalid when it is zero, so such a duel ends in `DUEL_BOTH_FORFEIT`.
+    pub struct DuelConfig {
+        /// Number of battlefields in play
+        pub battlefields: u8,
+        /// Power each player must distribute across the battlefields
+        pub budget: u16,
+        /// Score for winning each battlefield; a weight of 0 counts as 1,
+        /// so an all-zero array is an unweighted duel. Entries past
+        /// `battlefields` are ignored
+        pub weights: [u64; DUEL_MAX_BATTLEFIELDS],
+    }
+
+    /// A player's secret allocation for a configurable duel
+    pub struct ConfiguredDuelAllocation {
+        /// Power committed to each battlefield; entries past
+        /// `DuelConfig::battlefields` must be zero
+        pub power: [u16; DUEL_MAX_BATTLEFIELDS],
+    }
+
+    /// Publicly revealed result of a configurable duel
+    pub struct ConfiguredDuelOutcome {
+        /// One of the `DUEL_*` winner codes
+        pub winner: u8,
+        /// Per-battlefield result, as in `DuelOutcome::rounds`
+        pub rounds: [u8; DUEL_MAX_BATTLEFIELDS],
+        /// Which `DUEL_TIEBREAK_*` rule decided the duel
+        pub tiebreak: u8,
+    }
+
+    /// The classic Shadow Duel as a `DuelConfig`
+    fn shadow_duel_config() -> DuelConfig {
+        DuelConfig {
+            battlefields: DUEL_BATTLEFIELDS as u8,
+            budget: DUEL_BUDGET as u16,
+            weights: [0; DUEL_MAX_BATTLEFIELDS],
+        }
+    }
+
+    /// Score for winning battlefield `i`, with a weight of 0 counting as 1
+    fn battlefield_weight(config: &DuelConfig, i: usize) -> u64 {
+        if config.weights[i] == 0 {
+            1
+        } else {
+            config.weights[i]
+        }
+    }
+
+    /// Whether an allocation spends exactly the budget, on battlefields in play
+    /// 
+    /// Battlefields past `DUEL_MAX_BATTLEFIELDS` never exist, and no
+    /// allocation is valid under a config with zero battlefields.
+    fn is_valid_allocation(config: &DuelConfig, allocation: &ConfiguredDuelAllocation) -> bool {
+        let mut total: u32 = 0;
+        let mut out_of_play = false;
+        for i in 0..DUEL_MAX_BATTLEFIELDS {
+            if i < config.battlefields as usize {
+                total += allocation.power[i] as u32;
+            } else if allocation.power[i] != 0 {
+                out_of_play = true;
+            }
+        }
+
+        (config.battlefields > 0) && !out_of_play && (total == config.budget as u32)
+    }
+
+    /// Scores a duel between two allocations under `config`
+    /// 
+    /// A player whose allocation is invalid forfeits. Otherwise the player
+    /// with the higher total weight of battlefields won wins. Ties are
+    /// separated by the total power each player committed to the
+    /// battlefields they won, and if that is equal too, by a coin flip
+    /// from `ArcisRNG`, which neither player can influence.
+    fn score_duel(
+        config: &DuelConfig,
+        creator: &ConfiguredDuelAllocation,
+        opponent: &ConfiguredDuelAllocation,
+    ) -> ConfiguredDuelOutcome {
+        let creator_valid = is_valid_allocation(config, creator);
+        let opponent_valid = is_valid_allocation(config, opponent);
+        let scored = creator_valid && opponent_valid;
+
+        let mut rounds = [0u8; DUEL_MAX_BATTLEFIELDS];
+        let mut creator_score: u128 = 0;
+        let mut opponent_score: u128 = 0;
+        let mut creator_won_power: u32 = 0;
+        let mut opponent_won_power: u32 = 0;
+        for i in 0..DUEL_MAX_BATTLEFIELDS {
+            let a = creator.power[i];
+            let b = opponent.power[i];
+            let in_play = i < config.battlefields as usize;
+            let result = if scored && in_play { compare_values(a as u64, b as u64) } else { 0 };
+            rounds[i] = result;
+            if result == 1 {
+                creator_score += battlefield_weight(config, i) as u128;
+                creator_won_power += a as u32;
+            }
+            if result == 2 {
+                opponent_score += battlefield_weight(config, i) as u128;
+                opponent_won_power += b as u32;
+            }
+        }
+
+        // Evaluated unconditionally so the flip doesn't depend on the inputs
+        let creator_wins_flip = ArcisRNG::bool();
+
+        let mut tiebreak = DUEL_TIEBREAK_NONE;
+        let winner = if !creator_valid && !opponent_valid {
+            DUEL_BOTH_FORFEIT
+        } else if !creator_valid {
+            DUEL_CREATOR_FORFEIT
+        } else if !opponent_valid {
+            DUEL_OPPONENT_FORFEIT
+        } else if creator_score > opponent_score {
+            DUEL_CREATOR_WINS
+        } else if opponent_score > creator_score {
+            DUEL_OPPONENT_WINS
+        } else if creator_won_power != opponent_won_power {
+            tiebreak = DUEL_TIEBREAK_WON_POWER;
+            if creator_won_power > opponent_won_power {
+                DUEL_CREATOR_WINS
+            } else {
+                DUEL_OPPONENT_WINS
+            }
+        } else {
+            tiebreak = DUEL_TIEBREAK_RANDOM;
+            if creator_wins_flip {
+                DUEL_CREATOR_WINS
+            } else {
+                DUEL_OPPONENT_WINS
+            }
+        };
+
+        ConfiguredDuelOutcome {
+            winner,
+            rounds,
+            tiebreak,
+        }
+    }
+
+    /// Widens a classic allocation to the configurable layout
+    fn configured_allocation(allocation: &DuelAllocation) -> ConfiguredDuelAllocation {
+        let mut power = [0u16; DUEL_MAX_BATTLEFIELDS];
+        for i in 0..DUEL_BATTLEFIELDS {
+            power[i] = allocation.power[i] as u16;
+        }
+
+        ConfiguredDuelAllocation { power }
+    }
+
+    /// Resolves a Shadow Duel without revealing either allocation
+    /// 
+    /// Each player encrypts their allocation under their own key, so
+    /// neither can see the other's and there is no reveal phase to stall.
+    /// A player whose allocation doesn't sum to `DUEL_BUDGET` forfeits.
+    /// 
+    /// Players who won the same number of rounds are separated by the total
+    /// power they committed to the rounds they won, and if that is equal
+    /// too, by a coin flip from `ArcisRNG`. Neither player can influence
+    /// the flip, unlike a client-side `Math.random()`.
+    /// 
+    /// # Arguments
+    /// * `creator` - The creator's encrypted allocation
+    /// * `opponent` - The opponent's encrypted allocation
+    /// 
+    /// # Returns
+    /// * The winner code, per-round results and the tiebreak rule applied
+    #[instruction]
+    pub fn resolve_shadow_duel(
+        creator: Enc<Shared, DuelAllocation>,
+        opponent: Enc<Shared, DuelAllocation>,
+    ) -> DuelOutcome {
+        let creator_power = configured_allocation(&creator.to_arcis());
+        let opponent_power = configured_allocation(&opponent.to_arcis());
+
+        let outcome = score_duel(&shadow_duel_config(), &creator_power, &opponent_power);
+
+        let mut rounds = [0u8; DUEL_BATTLEFIELDS];
+        for i in 0..DUEL_BATTLEFIELDS {
+            rounds[i] = outcome.rounds[i];
+        }
+
+        DuelOutcome {
+            winner: outcome.winner,
+            rounds,
+            tiebreak: outcome.tiebreak,
+        }
+        .reveal()
+    }
+
+    /// Resolves a generalized Colonel Blotto duel under a public config
+    /// 
+    /// Same as `resolve_shadow_duel`, but the number of battlefields, the
+    /// budget and the per-battlefield weights come from `config`, so game
+    /// creators can offer different variants. Scores are compared by total
+    /// weight of battlefields won rather than by count.
+    /// 
+    /// # Arguments
+    /// * `config` - Public duel parameters
+    /// * `creator` - The creator's encrypted allocation
+    /// * `opponent` - The opponent's encrypted allocation
+    /// 
+    /// # Returns
+    /// * The winner code, per-battlefield results and the tiebreak rule applied
+    #[instruction]
+    pub fn resolve_configured_duel(
+        config: DuelConfig,
+        creator: Enc<Shared, ConfiguredDuelAllocation>,
+        opponent: Enc<Shared, ConfiguredDuelAllocation>,
+    ) -> ConfiguredDuelOutcome {
+        let creator_power = creator.to_arcis();
+        let opponent_power = opponent.to_arcis();
+
+        score_duel(&config, &creator_power, &opponent_power).reveal()
+    }
+
+    /// Shadow Duel held by the MXE between submission and showdown
+    /// 
+    /// Stored on-chain as `Enc<Mxe, DuelState>`, so neither allocation
+    /// leaks before its round is revealed with `reveal_round`.
+    pub struct DuelState {
+        pub creator: DuelAllocation,
+        pub opponent: DuelAllocation,
+        /// Whether both allocations were valid at submission
+        pub valid: bool,
+        /// Index of the next round to reveal
+        pub next_round: u8,
+        /// Rounds won so far by each player
+        pub creator_score: u8,
+        pub opponent_score: u8,
+    }
+
+    /// Publicly revealed result of a single `reveal_round` call
+    /// 
+    /// A refused reveal is all zero apart from `winner`.
+    pub struct RoundReveal {
+        /// Whether the requested round was revealed
+        pub accepted: bool,
+        pub creator_power: u8,
+        pub opponent_power: u8,
+        /// Running score after this round
+        pub creator_score: u8,
+        pub opponent_score: u8,
+        /// `DUEL_IN_PROGRESS` until the last round, then the final winner code
+        pub winner: u8,
+        /// Which `DUEL_TIEBREAK_*` rule decided the duel (after the last round)
+        pub tiebreak: u8,
+    }
+
+    /// Stores both players' allocations for a round-by-round showdown
+    /// 
+    /// # Returns
+    /// * The duel state, encrypted to the MXE
+    /// * `DUEL_IN_PROGRESS` if both allocations are valid, otherwise the
+    ///   forfeit code, which is final
+    #[instruction]
+    pub fn submit_duel(
+        mxe: Mxe,
+        creator: Enc<Shared, DuelAllocation>,
+        opponent: Enc<Shared, DuelAllocation>,
+    ) -> (Enc<Mxe, DuelState>, u8) {
+        let creator_power = creator.to_arcis();
+        let opponent_power = opponent.to_arcis();
+
+        let config = shadow_duel_config();
+        let creator_valid = is_valid_allocation(&config, &configured_allocation(&creator_power));
+        let opponent_valid = is_valid_allocation(&config, &configured_allocation(&opponent_power));
+
+        let status = if !creator_valid && !opponent_valid {
+            DUEL_BOTH_FORFEIT
+        } else if !creator_valid {
+            DUEL_CREATOR_FORFEIT
+        } else if !opponent_valid {
+            DUEL_OPPONENT_FORFEIT
+        } else {
+            DUEL_IN_PROGRESS
+        };
+
+        (
+            mxe.from_arcis(DuelState {
+                creator: creator_power,
+                opponent: opponent_power,
+                valid: creator_valid && opponent_valid,
+                next_round: 0,
+                creator_score: 0,
+                opponent_score: 0,
+            }),
+            status.reveal(),
+        )
+    }
+
+    /// Reveals round `round` (0-based) of a submitted duel
+    /// 
+    /// Only the round's two power values and the running score are
+    /// revealed. Rounds must be revealed in order and each only once; any
+    /// other request is refused and leaves the state unchanged. Revealing
+    /// the last round also reveals the winner, using the same rules as
+    /// `resolve_shadow_duel`.
+    /// 
+    /// # Returns
+    /// * The updated duel state, re-encrypted to the MXE
+    /// * The round's reveal
+    #[instruction]
+    pub fn reveal_round(
+        state: Enc<Mxe, DuelState>,
+        round: u8,
+    ) -> (Enc<Mxe, DuelState>, RoundReveal) {
+        let mut duel = state.to_arcis();
+
+        let accepted = duel.valid
+            && (round == duel.next_round)
+            && ((round as usize) < DUEL_BATTLEFIELDS);
+
+        let mut a: u8 = 0;
+        let mut b: u8 = 0;
+        for i in 0..DUEL_BATTLEFIELDS {
+            if accepted && (i == round as usize) {
+                a = duel.creator.power[i];
+                b = duel.opponent.power[i];
+            }
+        }
+
+        if accepted {
+            let result = compare_values(a as u64, b as u64);
+            if result == 1 {
+                duel.creator_score += 1;
+            }
+            if result == 2 {
+                duel.opponent_score += 1;
+            }
+            duel.next_round += 1;
+        }
+
+        // Every round is public once the last one is out, so scoring the
+        // whole duel here reveals nothing new
+        let finished = accepted && ((duel.next_round as usize) == DUEL_BATTLEFIELDS);
+        let outcome = score_duel(
+            &shadow_duel_config(),
+            &configured_allocation(&duel.creator),
+            &configured_allocation(&duel.opponent),
+        );
+
+        let reveal = RoundReveal {
+            accepted,
+            creator_power: a,
+            opponent_power: b,
+            creator_score: if accepted { duel.creator_score } else { 0 },
+            opponent_score: if accepted { duel.opponent_score } else { 0 },
+            winner: if finished { outcome.winner } else { DUEL_IN_PROGRESS },
+            tiebreak: if finished { outcome.tiebreak } else { DUEL_TIEBREAK_NONE },
+        };
+
+        (state.owner.from_arcis(duel), reveal.reveal())
     }
 }
 
@@ -28,6 +28,91 @@ export const ARCIUM_CONFIG = {
   encryptionEnabled: true,
 };
 
+/**
+ * Status codes revealed by `validate_transfer_status` and the other MPC
+ * instructions that report a rejection reason.
+ * Stable ABI: mirrors the `TRANSFER_*` constants in arcis/src/lib.rs.
+ */
+export const TRANSFER_STATUS = {
+  OK: 0,
+  ZERO_AMOUNT: 1,
+  INSUFFICIENT_FUNDS: 2,
+  EXCEEDS_TX_LIMIT: 3,
+  FEE_OVERFLOW: 4,
+  MINT_MISMATCH: 5,
+  DAILY_LIMIT_EXCEEDED: 6,
+  REPLAYED: 7,
+  RECIPIENT_OVERFLOW: 8,
+  ALLOWANCE_REJECTED: 9,
+  SELF_TRANSFER: 10,
+  ACCOUNT_MISMATCH: 11,
+  INVALID_TERMS: 12,
+} as const;
+
+export type TransferStatus = (typeof TRANSFER_STATUS)[keyof typeof TRANSFER_STATUS];
+
+const TRANSFER_STATUS_MESSAGES: Record<TransferStatus, string> = {
+  [TRANSFER_STATUS.OK]: "Transfer is valid",
+  [TRANSFER_STATUS.ZERO_AMOUNT]: "Amount must be greater than zero",
+  [TRANSFER_STATUS.INSUFFICIENT_FUNDS]: "Insufficient balance",
+  [TRANSFER_STATUS.EXCEEDS_TX_LIMIT]: "Amount exceeds the per-transaction limit",
+  [TRANSFER_STATUS.FEE_OVERFLOW]: "Amount plus network fee is too large",
+  [TRANSFER_STATUS.MINT_MISMATCH]: "Token does not match the sending account",
+  [TRANSFER_STATUS.DAILY_LIMIT_EXCEEDED]: "Amount exceeds the remaining daily limit",
+  [TRANSFER_STATUS.REPLAYED]: "Transfer was already submitted or is out of date",
+  [TRANSFER_STATUS.RECIPIENT_OVERFLOW]: "Recipient balance cannot accept this amount",
+  [TRANSFER_STATUS.ALLOWANCE_REJECTED]: "Allowance does not cover this transfer",
+  [TRANSFER_STATUS.SELF_TRANSFER]: "Cannot transfer to the same account",
+  [TRANSFER_STATUS.ACCOUNT_MISMATCH]: "Account settings do not belong to the sending account",
+  [TRANSFER_STATUS.INVALID_TERMS]: "Vesting or stream terms are invalid",
+};
+
+/**
+ * Human-readable message for a revealed transfer status code
+ */
+export function describeTransferStatus(status: number): string {
+  return TRANSFER_STATUS_MESSAGES[status as TransferStatus] ?? `Unknown transfer status (${status})`;
+}
+
+// Encrypted memo layout: mirrors `Memo` in arcis/src/lib.rs
+export const MEMO_BYTES = 64;
+const MEMO_WORD_BYTES = 16;
+
+/**
+ * Pack a memo into the little-endian u128 words of the MPC `Memo` struct
+ */
+export function packMemo(memo: string): bigint[] {
+  const bytes = new TextEncoder().encode(memo);
+  if (bytes.length > MEMO_BYTES) {
+    throw new Error(`Memo is longer than ${MEMO_BYTES} bytes`);
+  }
+
+  const words: bigint[] = [];
+  for (let w = 0; w < MEMO_BYTES / MEMO_WORD_BYTES; w++) {
+    let word = BigInt(0);
+    for (let i = MEMO_WORD_BYTES - 1; i >= 0; i--) {
+      word = (word << BigInt(8)) | BigInt(bytes[w * MEMO_WORD_BYTES + i] ?? 0);
+    }
+    words.push(word);
+  }
+  return words;
+}
+
+/**
+ * Unpack decrypted `Memo` words back into a string (zero padding is dropped)
+ */
+export function unpackMemo(words: bigint[]): string {
+  const bytes = new Uint8Array(MEMO_BYTES);
+  words.forEach((word, w) => {
+    for (let i = 0; i < MEMO_WORD_BYTES; i++) {
+      bytes[w * MEMO_WORD_BYTES + i] = Number((word >> BigInt(8 * i)) & BigInt(0xff));
+    }
+  });
+
+  const end = bytes.indexOf(0);
+  return new TextDecoder().decode(bytes.subarray(0, end === -1 ? MEMO_BYTES : end));
+}
+
 // Track if Arcium SDK is available
 let arciumSdkAvailable = false;
 let arciumClient: typeof import("@arcium-hq/client") | null = null;